@@ -1,7 +1,8 @@
 use criterion::{black_box, Criterion, Throughput};
+use utf_c::Options;
 
-pub fn compress(text: &str) -> Vec<u8> {
-    match utf_c::compress(text) {
+pub fn compress(text: &str, options: &Options) -> Vec<u8> {
+    match utf_c::compress_with(text, options) {
         Ok(r) => black_box(r),
         Err(e) => panic!("Compression failed: {:?}", e)
     }
@@ -15,21 +16,34 @@ pub fn decompress(bytes: &[u8]) -> Vec<u8> {
 }
 
 fn benchmark(c: &mut Criterion) {
-    let texts: [&str; 3] = [
-        &"טקסט זה נדחס עם UTF-C ו-GZIP ולאחר מכן הושווה. טקסט זה תורגם עם Google Translate ואנו מקווים שהוא תורגם כהלכה, אך אין ערובה לכך",
-        &"A".repeat(4 * 1024), // BASIC | 41
-        &"𖽁".repeat(4 * 1024), // MIAO  | f0 96 bd 81
+    const CHAT: &str = "Hey 👋🏽 are you coming tonight? 🎉🎉 The whole family 👨‍👩‍👧 is here ❤️ and we watch the game 🇩🇪 vs 🇫🇷 ⚽️ 😂😂👍🏻";
+
+    const CYRILLIC: &str = "Этот текст был сжат с помощью UTF-C и GZIP, а затем сравнён. Этот текст был переведён с помощью Google Translate, и мы надеемся, что он переведён правильно, но гарантии нет. ";
+    const GREEK: &str = "Αυτό το κείμενο συμπιέστηκε με UTF-C και GZIP και στη συνέχεια συγκρίθηκε. Αυτό το κείμενο μεταφράστηκε με το Google Translate και ελπίζουμε ότι μεταφράστηκε σωστά, αλλά δεν υπάρχει καμία εγγύηση. ";
+    const CHINESE: &str = "这段文字先用UTF-C和GZIP压缩，然后进行比较。这段文字是用谷歌翻译翻译的，我们希望翻译正确，但不能保证。";
+    const HEBREW: &str = "טקסט זה נדחס עם UTF-C ו-GZIP ולאחר מכן הושווה. טקסט זה תורגם עם Google Translate ואנו מקווים שהוא תורגם כהלכה, אך אין ערובה לכך. ";
+
+    let texts: [(&str, Options); 9] = [
+        ("טקסט זה נדחס עם UTF-C ו-GZIP ולאחר מכן הושווה. טקסט זה תורגם עם Google Translate ואנו מקווים שהוא תורגם כהלכה, אך אין ערובה לכך", Options::new()),
+        (&"A".repeat(4 * 1024), Options::new()), // BASIC | 41
+        (&"𖽁".repeat(4 * 1024), Options::new()), // MIAO  | f0 96 bd 81
+        (CHAT, Options::new()),                  // EMOJI
+        (CHAT, Options::new().emoji(true)),      // EMOJI (with the emoji codes)
+        (&CYRILLIC.repeat(32), Options::new()),  // CYRILLIC | d0 xx, d1 xx
+        (&GREEK.repeat(32), Options::new()),     // GREEK    | ce xx, cf xx
+        (&HEBREW.repeat(32), Options::new()),    // HEBREW   | d7 xx
+        (&CHINESE.repeat(32), Options::new()),   // CJK      | e4 xx, e5 xx, ... (prefix changes)
     ];
 
-    for (idx, &text) in texts.iter().enumerate() {
+    for (idx, (text, options)) in texts.iter().enumerate() {
         let group_name = format!("{}", idx);
         let mut group = c.benchmark_group(group_name);
         group.sample_size(250);
 
         group.throughput(Throughput::Bytes(text.len() as u64));
-        group.bench_function("compression", |b| b.iter(|| compress(text)));
+        group.bench_function("compression", |b| b.iter(|| compress(text, options)));
 
-        let compressed_bytes = compress(text);
+        let compressed_bytes = compress(text, options);
 
         group.throughput(Throughput::Bytes(text.len() as u64));
         group.bench_function("decompression", |b| b.iter(|| decompress(&compressed_bytes)));
@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+
+/// A destination for the bytes produced by the compression and decompression loops.
+pub(crate) trait Buffer {
+    /// Appends one byte.
+    fn push(&mut self, byte: u8);
+
+    /// Appends all bytes of the given slice.
+    fn extend_from_slice(&mut self, bytes: &[u8]);
+
+    /// Appends the given byte `count` times.
+    fn fill(&mut self, byte: u8, count: usize);
+
+    /// Returns the number of bytes that were (or would have been) written.
+    fn len(&self) -> usize;
+
+    /// Removes all bytes after the first `len` bytes.
+    fn truncate(&mut self, len: usize);
+}
+
+impl Buffer for Vec<u8> {
+    #[inline(always)]
+    fn push(&mut self, byte: u8) {
+        Vec::push(self, byte);
+    }
+
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        Vec::extend_from_slice(self, bytes);
+    }
+
+    #[inline(always)]
+    fn fill(&mut self, byte: u8, count: usize) {
+        self.resize(self.len() + count, byte);
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+}
+
+/// A buffer that writes into a fixed-size slice.
+///
+/// If the slice is too small, the remaining bytes are not written, but they are still counted,
+/// so that the required length is known after the loop has finished.
+pub(crate) struct SliceBuffer<'a> {
+    bytes: &'a mut [u8],
+    len: usize,
+}
+
+impl SliceBuffer<'_> {
+    /// Returns `true` if all bytes fit into the slice, otherwise `false`.
+    #[inline]
+    pub fn fits(&self) -> bool {
+        self.len <= self.bytes.len()
+    }
+}
+
+impl Buffer for SliceBuffer<'_> {
+    #[inline(always)]
+    fn push(&mut self, byte: u8) {
+        if let Some(dst) = self.bytes.get_mut(self.len) {
+            *dst = byte;
+        }
+        self.len += 1;
+    }
+
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let end = self.len + bytes.len();
+        if let Some(dst) = self.bytes.get_mut(self.len..end) {
+            dst.copy_from_slice(bytes);
+        }
+        self.len = end;
+    }
+
+    #[inline(always)]
+    fn fill(&mut self, byte: u8, count: usize) {
+        let end = self.len + count;
+        if let Some(dst) = self.bytes.get_mut(self.len..end) {
+            dst.fill(byte);
+        }
+        self.len = end;
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+}
+
+impl<'a> From<&'a mut [u8]> for SliceBuffer<'a> {
+    #[inline]
+    fn from(value: &'a mut [u8]) -> Self {
+        Self { bytes: value, len: 0 }
+    }
+}
+
+/// A buffer that only counts the bytes, without writing them anywhere.
+#[derive(Default)]
+pub(crate) struct CountBuffer(usize);
+
+impl Buffer for CountBuffer {
+    #[inline(always)]
+    fn push(&mut self, _byte: u8) {
+        self.0 += 1;
+    }
+
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.0 += bytes.len();
+    }
+
+    #[inline(always)]
+    fn fill(&mut self, _byte: u8, count: usize) {
+        self.0 += count;
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.0
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, len: usize) {
+        self.0 = self.0.min(len);
+    }
+}
@@ -0,0 +1,41 @@
+/// The length of the checksum trailer.
+pub(crate) const CHECKSUM_LEN: usize = 4;
+
+/// The reversed polynomial of CRC-32C (Castagnoli).
+const POLYNOMIAL: u32 = 0x82F6_3B78;
+
+/// The lookup table for one byte at a time.
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+/// Returns the CRC-32C of `bytes`.
+pub(crate) fn crc32c(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn crc32c() {
+        // Check values from RFC 3720 (B.4) and the common "123456789" test.
+        assert_eq!(super::crc32c(b""), 0);
+        assert_eq!(super::crc32c(b"123456789"), 0xE306_9283);
+        assert_eq!(super::crc32c(&[0; 32]), 0x8A91_36AA);
+        assert_eq!(super::crc32c(&[0xFF; 32]), 0x62A8_AB43);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{utf8, Options};
+
+// The following codes use bytes that are never the first byte of a character (RFC 3629),
+// so they can't be confused with a prefix or a character.
+
+/// U+200D (ZERO WIDTH JOINER), without changing the prefix.
+pub(crate) const ZWJ: u8 = 0xC0;
+/// U+FE0F (VARIATION SELECTOR-16), without changing the prefix.
+pub(crate) const VS16: u8 = 0xC1;
+/// The first byte of the codes `0xF5`-`0xFF`.
+pub(crate) const FIRST_CODE: u8 = 0xF5;
+/// Enters a wide window (followed by the selector), see [`Window::Wide`].
+pub(crate) const WIDE_WINDOW: u8 = 0xF5;
+/// Enters a narrow window (followed by the selector), see [`Window::Narrow`].
+pub(crate) const NARROW_WINDOW: u8 = 0xF6;
+/// An emoji modifier U+1F3FB-U+1F3FF (followed by the last byte), without changing the prefix.
+pub(crate) const EMOJI_MODIFIER: u8 = 0xF7;
+/// Two regional indicators U+1F1E6-U+1F1FF (followed by the last byte of each), without changing the prefix.
+pub(crate) const REGIONAL_INDICATORS: u8 = 0xF8;
+/// Enters the last window again.
+pub(crate) const LAST_WINDOW: u8 = 0xF9;
+/// The first byte of a back-reference to the prefix cache (`0xFC`-`0xFF`, one per slot).
+///
+/// A 2- or 3-byte prefix replaces the current prefix, a 1-byte prefix is followed by a single character.
+pub(crate) const CACHE_SLOT: u8 = 0xFC;
+/// Leaves a window (`ESC`), or is a literal `ESC` if written twice.
+pub(crate) const WINDOW_EXIT: u8 = 0x1B;
+
+/// The UTF-8 bytes of U+200D.
+pub(crate) const ZWJ_BYTES: [u8; 3] = [0xE2, 0x80, 0x8D];
+/// The UTF-8 bytes of U+FE0F.
+pub(crate) const VS16_BYTES: [u8; 3] = [0xEF, 0xB8, 0x8F];
+/// The UTF-8 prefix of the emoji modifiers.
+pub(crate) const EMOJI_MODIFIER_PREFIX: [u8; 3] = [0xF0, 0x9F, 0x8F];
+/// The last bytes of the emoji modifiers.
+pub(crate) const EMOJI_MODIFIER_CHARS: core::ops::RangeInclusive<u8> = 0xBB..=0xBF;
+/// The UTF-8 prefix of the regional indicators.
+pub(crate) const REGIONAL_INDICATOR_PREFIX: [u8; 3] = [0xF0, 0x9F, 0x87];
+/// The last bytes of the regional indicators.
+pub(crate) const REGIONAL_INDICATOR_CHARS: core::ops::RangeInclusive<u8> = 0xA6..=0xBF;
+
+/// Returns `true` if the byte is a code instead of a prefix or a character, otherwise `false`.
+#[inline(always)]
+pub(crate) const fn is_code(byte: u8) -> bool {
+    byte >= FIRST_CODE || (byte & 0b11111110) == ZWJ
+}
+/// The number of prefixes in the prefix cache.
+pub(crate) const CACHE_SLOTS: usize = 4;
+
+/// The last prefixes before the current one, in a ring of slots.
+///
+/// A back-reference to a 1-byte prefix is followed by a single character and doesn't replace the current prefix,
+/// because only then it is shorter than switching to the prefix and back.
+/// The compressor and the decompressor must update the cache in the same way, so that the slots are the same.
+/// The cache is only updated if it is enabled ([`Options::prefix_cache`]), so it doesn't slow down other data.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct PrefixCache {
+    prefixes: [utf8::Prefix; CACHE_SLOTS],
+    /// The number of used slots.
+    len: u8,
+    /// The slot that is replaced by the next prefix.
+    next: u8,
+    enabled: bool,
+}
+
+impl PrefixCache {
+    /// Creates an empty cache, which is only updated if it is `enabled`.
+    #[inline]
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, ..Self::default() }
+    }
+
+    /// Returns the slot of the prefix, if it is in the cache.
+    #[inline]
+    pub fn find(&self, prefix: &[u8]) -> Option<usize> {
+        self.prefixes[..(self.len as usize)].iter().position(|cached| cached.as_slice() == prefix)
+    }
+
+    /// Returns the prefix of the slot, if the slot is used.
+    #[inline]
+    pub fn get(&self, slot: usize) -> Option<utf8::Prefix> {
+        self.prefixes[..(self.len as usize)].get(slot).copied()
+    }
+
+    /// Updates the cache after the current prefix `previous` has been replaced.
+    ///
+    /// The prefix replaces the oldest slot, unless it is already in the cache.
+    #[inline]
+    pub fn update(&mut self, previous: &[u8]) {
+        if !self.enabled || previous.is_empty() || self.find(previous).is_some() {
+            return;
+        }
+
+        self.prefixes[self.next as usize] = utf8::Prefix::from(previous);
+        self.next = (self.next + 1) % CACHE_SLOTS as u8;
+        self.len = (self.len + 1).min(CACHE_SLOTS as u8);
+    }
+}
+
+/// A range of code points, where each character is encoded relative to the beginning of the range.
+///
+/// Inside a window, ASCII characters are written as usual, and all other bytes belong to the characters of the window.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Window {
+    /// 32768 code points starting at `selector * 256`, with 2 bytes per character (`1xxxxxxx xxxxxxxx`).
+    ///
+    /// The last 256 offsets are always the halfwidth and fullwidth forms (U+FF00-U+FFFF),
+    /// so that e.g. the fullwidth punctuation of Chinese text doesn't leave the window.
+    Wide(u8),
+    /// 128 code points starting at `selector * 128`, with 1 byte per character (`1xxxxxxx`).
+    Narrow(u8),
+}
+
+/// The first offset of the halfwidth and fullwidth forms in a wide window.
+const WIDE_FORMS_OFFSET: u32 = 0x7F00;
+/// The first code point of the halfwidth and fullwidth forms.
+const FORMS_BASE: u32 = 0xFF00;
+
+impl Window {
+    /// Returns the first code point of the window.
+    #[inline]
+    pub const fn base(self) -> u32 {
+        match self {
+            Self::Wide(selector) => (selector as u32) << 8,
+            Self::Narrow(selector) => (selector as u32) << 7,
+        }
+    }
+
+    /// Returns the offset of the code point in the window, if it is inside the window.
+    #[inline]
+    pub const fn offset(self, code_point: u32) -> Option<u32> {
+        let size = match self {
+            Self::Wide(_) if code_point >= FORMS_BASE && code_point <= 0xFFFF => return Some(code_point - FORMS_BASE + WIDE_FORMS_OFFSET),
+            Self::Wide(_) => WIDE_FORMS_OFFSET,
+            Self::Narrow(_) => 0x80,
+        };
+        if code_point >= self.base() && (code_point - self.base()) < size {
+            Some(code_point - self.base())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the code point of the offset in the window.
+    #[inline]
+    pub const fn code_point(self, offset: u32) -> u32 {
+        match self {
+            Self::Wide(_) if offset >= WIDE_FORMS_OFFSET => FORMS_BASE + (offset - WIDE_FORMS_OFFSET),
+            _ => self.base() + offset,
+        }
+    }
+}
+
+/// The state that is kept between multiple inputs of the same compressed bytes (e.g. the chunks of a stream).
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Context {
+    /// The last written or read prefix.
+    pub prefix: utf8::Prefix,
+    pub cache: PrefixCache,
+    /// The current window, if any.
+    pub window: Option<Window>,
+    /// The last entered window (for [`LAST_WINDOW`]).
+    pub last_window: Option<Window>,
+}
+
+impl Context {
+    /// Creates the context for the beginning of the compressed bytes with the given `options`.
+    #[inline]
+    pub fn new(options: &Options) -> Self {
+        Self { cache: PrefixCache::new(options.get_prefix_cache()), ..Self::default() }
+    }
+
+    /// Enters the window.
+    #[inline]
+    pub fn enter(&mut self, window: Window) {
+        self.window = Some(window);
+        self.last_window = Some(window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn prefix_cache() {
+        let mut cache = super::PrefixCache::new(true);
+        cache.update(&[]);
+        cache.update(&[224, 164]);
+        cache.update(&[224, 165]);
+        cache.update(&[215]);
+        assert_eq!(cache.find(&[224, 164]), Some(0));
+        assert_eq!(cache.find(&[224, 165]), Some(1));
+        assert_eq!(cache.find(&[215]), Some(2));
+
+        // A prefix is only stored once.
+        cache.update(&[224, 164]);
+        assert_eq!(cache.find(&[224, 164]), Some(0));
+        assert!(cache.get(3).is_none());
+
+        // The oldest prefix is replaced if the cache is full.
+        for prefix in [[225, 128], [225, 129]] {
+            cache.update(&prefix);
+        }
+        assert_eq!(cache.find(&[224, 164]), None);
+        assert_eq!(cache.find(&[225, 129]), Some(0));
+        assert!(cache.get(super::CACHE_SLOTS).is_none());
+
+        // A disabled cache is never updated.
+        let mut cache = super::PrefixCache::default();
+        cache.update(&[224, 164]);
+        assert_eq!(cache.find(&[224, 164]), None);
+    }
+
+    #[test]
+    fn window() {
+        use super::Window;
+
+        let test_cases: [(Window, u32, Option<u32>); 7] = [
+            (Window::Wide(0x2E),   0x4E2D, Some(0x202D)),
+            (Window::Wide(0x2E),   0xACFF, Some(0x7EFF)),
+            (Window::Wide(0x2E),   0xAD00, None),
+            (Window::Wide(0x2E),   0xFF0C, Some(0x7F0C)), // Fullwidth comma
+            (Window::Wide(0x80),   0xFF0C, Some(0x7F0C)),
+            (Window::Narrow(0x12), 0x0905, Some(0x05)),
+            (Window::Narrow(0x12), 0x0985, None),
+        ];
+
+        for (window, code_point, offset) in test_cases {
+            assert_eq!(window.offset(code_point), offset, "Offset does not match for: {:X}", code_point);
+            if let Some(offset) = offset {
+                assert_eq!(window.code_point(offset), code_point);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{buffer::Buffer, context, DecompressError};
+
+/// Writes the code for the character(s) at the beginning of `value` and returns the number of read bytes,
+/// or returns `0` if there is no shorter code.
+///
+/// The codes don't change the prefix, so that e.g. the characters of a ZWJ sequence can share one prefix.
+#[inline]
+pub(crate) fn compress<B: Buffer>(value: &[u8], prefix: &[u8], result: &mut B) -> usize {
+    match value {
+        // U+200D
+        [0xE2, 0x80, 0x8D, ..] => {
+            result.push(context::ZWJ);
+            3
+        },
+        // U+FE0F
+        [0xEF, 0xB8, 0x8F, ..] => {
+            result.push(context::VS16);
+            3
+        },
+        // U+1F3FB-U+1F3FF (only if the prefix would have to be written)
+        [0xF0, 0x9F, 0x8F, utf8_char @ 0xBB..=0xBF, ..] if prefix != context::EMOJI_MODIFIER_PREFIX => {
+            result.extend_from_slice(&[context::EMOJI_MODIFIER, *utf8_char]);
+            4
+        },
+        // U+1F1E6-U+1F1FF twice (only if the prefix would have to be written)
+        [0xF0, 0x9F, 0x87, first_char @ 0xA6..=0xBF, 0xF0, 0x9F, 0x87, second_char @ 0xA6..=0xBF, ..] if prefix != context::REGIONAL_INDICATOR_PREFIX => {
+            result.extend_from_slice(&[context::REGIONAL_INDICATORS, *first_char, *second_char]);
+            8
+        },
+        _ => 0,
+    }
+}
+
+/// Writes the character(s) of the code at the beginning of `value` and returns the number of read bytes or `DecompressError`.
+///
+/// The code must be [`context::ZWJ`], [`context::VS16`], [`context::EMOJI_MODIFIER`] or [`context::REGIONAL_INDICATORS`].
+#[inline]
+pub(crate) fn decompress<B: Buffer>(value: &[u8], result: &mut B) -> Result<usize, DecompressError> {
+    let code_len = match value[0] {
+        context::ZWJ | context::VS16 => 1,
+        context::EMOJI_MODIFIER => 2,
+        _ => 3,
+    };
+    let Some(code) = value.get(..code_len) else {
+        return Err(DecompressError::Truncated { at: 0 });
+    };
+
+    match *code {
+        [context::ZWJ] => result.extend_from_slice(&context::ZWJ_BYTES),
+        [context::VS16] => result.extend_from_slice(&context::VS16_BYTES),
+        [context::EMOJI_MODIFIER, utf8_char] if context::EMOJI_MODIFIER_CHARS.contains(&utf8_char) => {
+            result.extend_from_slice(&context::EMOJI_MODIFIER_PREFIX);
+            result.push(utf8_char);
+        },
+        [context::REGIONAL_INDICATORS, first_char, second_char]
+            if context::REGIONAL_INDICATOR_CHARS.contains(&first_char) && context::REGIONAL_INDICATOR_CHARS.contains(&second_char) =>
+        {
+            for utf8_char in [first_char, second_char] {
+                result.extend_from_slice(&context::REGIONAL_INDICATOR_PREFIX);
+                result.push(utf8_char);
+            }
+        },
+        _ => return Err(DecompressError::InvalidUtf8 { at: 0, bytes: code.to_vec() }),
+    }
+    Ok(code_len)
+}
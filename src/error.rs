@@ -0,0 +1,168 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::utf8;
+
+/// An error that can occur during compression.
+///
+/// All offsets are byte offsets in the uncompressed input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressError {
+    /// The input is empty.
+    InvalidLength,
+    /// The character at `at` has an invalid or missing prefix.
+    ///
+    /// `bytes` contains up to 4 bytes starting at `at`.
+    InvalidOrMissingPrefix { at: usize, bytes: Vec<u8> },
+    /// The output buffer is too small and needs at least `required` bytes.
+    BufferTooSmall { required: usize },
+    /// The character at `at` is an overlong encoding (e.g. `C0 80` instead of `00`).
+    OverlongEncoding { at: usize },
+    /// The character at `at` is a UTF-16 surrogate (U+D800-U+DFFF).
+    Surrogate { at: usize },
+    /// The character at `at` is above U+10FFFF.
+    OutOfRange { at: usize },
+}
+
+impl CompressError {
+    /// Moves the offset of the error by `offset` bytes (e.g. if the input was only a part of the whole input).
+    #[inline]
+    pub(crate) fn with_offset(mut self, offset: usize) -> Self {
+        if let 
+            Self::InvalidOrMissingPrefix { at, .. } | 
+            Self::OverlongEncoding { at } | 
+            Self::Surrogate { at } | 
+            Self::OutOfRange { at } = &mut self 
+        {
+            *at += offset;
+        }
+        self
+    }
+
+    /// Returns the error for the given `utf8::PrefixError` at `at`.
+    #[inline]
+    pub(crate) fn from_prefix_error(err: utf8::PrefixError, at: usize) -> Self {
+        match err {
+            utf8::PrefixError::Overlong => Self::OverlongEncoding { at },
+            utf8::PrefixError::Surrogate => Self::Surrogate { at },
+            utf8::PrefixError::OutOfRange => Self::OutOfRange { at },
+        }
+    }
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength => write!(f, "the input is empty"),
+            Self::InvalidOrMissingPrefix { at, bytes } => write!(f, "invalid or missing prefix at offset {} (bytes: {:02X?})", at, bytes),
+            Self::BufferTooSmall { required } => write!(f, "the output buffer is too small ({} bytes required)", required),
+            Self::OverlongEncoding { at } => write!(f, "overlong encoding at offset {}", at),
+            Self::Surrogate { at } => write!(f, "UTF-16 surrogate at offset {}", at),
+            Self::OutOfRange { at } => write!(f, "character above U+10FFFF at offset {}", at),
+        }
+    }
+}
+
+impl core::error::Error for CompressError {}
+
+/// An error that can occur during decompression.
+///
+/// All offsets are byte offsets in the compressed input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecompressError {
+    /// The input is too short to contain a length header and a character.
+    InvalidLength,
+    /// The input ends before the length header or the stream is complete.
+    MissingBytes,
+    /// The character at `at` has no prefix.
+    ///
+    /// `bytes` contains up to 4 bytes starting at `at`.
+    MissingPrefix { at: usize, bytes: Vec<u8> },
+    /// The output buffer is too small and needs at least `required` bytes.
+    BufferTooSmall { required: usize },
+    /// The character at `at` is not valid UTF-8.
+    ///
+    /// `bytes` contains the bytes of the decompressed character (prefix and character).
+    InvalidUtf8 { at: usize, bytes: Vec<u8> },
+    /// The length in the header does not fit into `usize`.
+    LengthOverflow,
+    /// The input does not start with a container header.
+    MissingContainer,
+    /// The container header has a format `version` that is not supported.
+    UnsupportedVersion { version: u8 },
+    /// The container header has `flags` that are not supported.
+    UnsupportedFlags { flags: u8 },
+    /// The checksum of the decompressed bytes (`actual`) does not match the stored checksum (`expected`).
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The code at `at` enters the last window, but no window has been entered before.
+    MissingWindow { at: usize },
+    /// The number of decompressed bytes (`actual`) does not match the length in the header (`expected`).
+    LengthMismatch { expected: usize, actual: usize },
+    /// The input ends at `at` in the middle of a character (e.g. after a prefix) or a code.
+    Truncated { at: usize },
+    /// The bytes from `at` are after the length in the header.
+    TrailingData { at: usize },
+    /// The block length in the frame header is `0`.
+    InvalidBlockLength,
+    /// The block at `at` decompresses to `len` bytes, which is more than the block length in the frame header.
+    BlockTooLong { at: usize, len: usize },
+}
+
+impl DecompressError {
+    /// Moves the offset of the error by `offset` bytes (e.g. if the input was only a part of the whole input).
+    #[inline]
+    pub(crate) fn with_offset(mut self, offset: usize) -> Self {
+        if let 
+            Self::MissingPrefix { at, .. } | 
+            Self::InvalidUtf8 { at, .. } | 
+            Self::MissingWindow { at } | 
+            Self::Truncated { at } | 
+            Self::TrailingData { at } = &mut self 
+        {
+            *at += offset;
+        }
+        self
+    }
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength => write!(f, "the input is too short"),
+            Self::MissingBytes => write!(f, "the input ends unexpectedly"),
+            Self::MissingPrefix { at, bytes } => write!(f, "missing prefix at offset {} (bytes: {:02X?})", at, bytes),
+            Self::BufferTooSmall { required } => write!(f, "the output buffer is too small ({} bytes required)", required),
+            Self::InvalidUtf8 { at, bytes } => write!(f, "invalid UTF-8 at offset {} (bytes: {:02X?})", at, bytes),
+            Self::LengthOverflow => write!(f, "the length in the header is too large"),
+            Self::MissingContainer => write!(f, "the container header is missing"),
+            Self::UnsupportedVersion { version } => write!(f, "unsupported format version {}", version),
+            Self::UnsupportedFlags { flags } => write!(f, "unsupported flags {:#010b}", flags),
+            Self::ChecksumMismatch { expected, actual } => write!(f, "checksum mismatch (expected: {:08X}, actual: {:08X})", expected, actual),
+            Self::MissingWindow { at } => write!(f, "missing window at offset {}", at),
+            Self::LengthMismatch { expected, actual } => write!(f, "length mismatch (expected: {} bytes, actual: {} bytes)", expected, actual),
+            Self::Truncated { at } => write!(f, "truncated character at offset {}", at),
+            Self::TrailingData { at } => write!(f, "trailing data at offset {}", at),
+            Self::InvalidBlockLength => write!(f, "the block length in the frame header is 0"),
+            Self::BlockTooLong { at, len } => write!(f, "the block at offset {} is too long ({} bytes)", at, len),
+        }
+    }
+}
+
+impl core::error::Error for DecompressError {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn display() {
+        let err = crate::compress([72, 0b10000000]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid or missing prefix at offset 1 (bytes: [80])");
+
+        let err = crate::decompress_to_string([5, 72, 215, 153, 215, 72]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-8 at offset 4 (bytes: [D7, D7])");
+
+        let err = crate::decompress_to_string([4, 72, 215, 153, 215]).unwrap_err();
+        assert_eq!(err.to_string(), "truncated character at offset 4");
+    }
+}
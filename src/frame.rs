@@ -0,0 +1,453 @@
+//! A framed format for large texts, which are split into independent blocks.
+//!
+//! Each block is compressed like [`compress`](crate::compress) (with its own length header) and starts with an empty prefix,
+//! so the blocks can be decompressed one at a time, in parallel or skipped without decompressing them:
+//! ```text
+//! frame  := header block* end
+//! header := 0x00 'U' 'F' + version (u8) + block length (u32, little-endian)
+//! block  := length (u32, little-endian, 1..) + compressed block
+//! end    := 0x00 0x00 0x00 0x00
+//! ```
+//! The block length in the header is the maximum length of the text of a block, so it can be used to allocate a buffer once.
+//! Each block ends at a character boundary.
+//!
+//! With the `rayon` feature, `compress_parallel` and `decompress_parallel` process the blocks on the thread pool of `rayon`.
+
+use alloc::vec::Vec;
+
+use crate::{utf8, CompressError, DecompressError};
+
+/// The magic bytes at the beginning of a frame.
+const MAGIC: [u8; 3] = [0x00, b'U', b'F'];
+/// The version of the framed format.
+const VERSION: u8 = 1;
+/// The length of the frame header (magic bytes, version and block length).
+const HEADER_LEN: usize = 8;
+/// The length of a block header.
+const BLOCK_HEADER_LEN: usize = 4;
+/// The end of a frame (a block with the length `0`).
+const END: [u8; BLOCK_HEADER_LEN] = [0; BLOCK_HEADER_LEN];
+
+/// The default length of the text of a block.
+pub const DEFAULT_BLOCK_LEN: usize = 64 * 1024;
+/// The minimum length of the text of a block (the longest character).
+pub const MIN_BLOCK_LEN: usize = utf8::MAX_UTF8_BYTES;
+/// The maximum length of the text of a block.
+pub const MAX_BLOCK_LEN: usize = 16 * 1024 * 1024;
+
+/// Returns the framed compressed bytes or `CompressError`.
+///
+/// The text is split into blocks of at most `block_len` bytes, which is clamped to [`MIN_BLOCK_LEN`]..=[`MAX_BLOCK_LEN`].
+/// Unlike [`compress`](crate::compress), the text can be empty.
+///
+/// # Example
+/// ```
+/// use utf_c::frame;
+///
+/// let compressed_bytes = frame::compress("ÄÖÜ", frame::DEFAULT_BLOCK_LEN).unwrap();
+/// assert_eq!(frame::decompress(compressed_bytes).unwrap(), "ÄÖÜ".as_bytes());
+/// ```
+pub fn compress<T>(bytes: T, block_len: usize) -> Result<Vec<u8>, CompressError>
+where
+    T: AsRef<[u8]>,
+{
+    let value = bytes.as_ref();
+    let block_len = block_len.clamp(MIN_BLOCK_LEN, MAX_BLOCK_LEN);
+
+    let mut result = Vec::with_capacity(HEADER_LEN + crate::max_compressed_len(value.len()) + END.len());
+    write_header(block_len, &mut result);
+
+    let mut offset = 0;
+    for block in split(value, block_len) {
+        compress_block(block, &mut result).map_err(|err| err.with_offset(offset))?;
+        offset += block.len();
+    }
+
+    result.extend_from_slice(&END);
+    Ok(result)
+}
+
+/// Returns the decompressed bytes of a frame or `DecompressError`.
+///
+/// # Example
+/// ```
+/// use utf_c::frame;
+///
+/// let compressed_bytes = frame::compress("Hello Wörld", 4).unwrap();
+/// assert_eq!(frame::decompress(compressed_bytes).unwrap(), "Hello Wörld".as_bytes());
+/// ```
+pub fn decompress<T>(bytes: T) -> Result<Vec<u8>, DecompressError>
+where
+    T: AsRef<[u8]>,
+{
+    let blocks = blocks(bytes.as_ref())?;
+    let mut result = Vec::new();
+    for block in blocks {
+        block?.decompress_into(&mut result)?;
+    }
+    Ok(result)
+}
+
+/// Returns the framed compressed bytes or `CompressError`, compressing the blocks in parallel.
+///
+/// The result is the same as with [`compress`], so it can also be decompressed with [`decompress`].
+/// If multiple blocks are invalid, the error of any of them may be returned.
+///
+/// # Example
+/// ```
+/// use utf_c::frame;
+///
+/// let text = "Hello Wörld ".repeat(1024);
+/// let compressed_bytes = frame::compress_parallel(&text, 1024).unwrap();
+/// assert_eq!(compressed_bytes, frame::compress(&text, 1024).unwrap());
+/// ```
+#[cfg(feature = "rayon")]
+pub fn compress_parallel<T>(bytes: T, block_len: usize) -> Result<Vec<u8>, CompressError>
+where
+    T: AsRef<[u8]>,
+{
+    use rayon::prelude::*;
+
+    let value = bytes.as_ref();
+    let block_len = block_len.clamp(MIN_BLOCK_LEN, MAX_BLOCK_LEN);
+
+    // The blocks are split sequentially, which is fast compared to the compression.
+    let mut offset = 0;
+    let blocks = split(value, block_len).map(|block| {
+        offset += block.len();
+        (offset - block.len(), block)
+    }).collect::<Vec<_>>();
+
+    let compressed_blocks = blocks.into_par_iter().map(|(offset, block)| {
+        let mut result = Vec::with_capacity(BLOCK_HEADER_LEN + crate::max_compressed_len(block.len()));
+        compress_block(block, &mut result).map_err(|err| err.with_offset(offset))?;
+        Ok(result)
+    }).collect::<Result<Vec<_>, CompressError>>()?;
+
+    let compressed_len = compressed_blocks.iter().map(Vec::len).sum::<usize>();
+    let mut result = Vec::with_capacity(HEADER_LEN + compressed_len + END.len());
+    write_header(block_len, &mut result);
+    for compressed_block in compressed_blocks {
+        result.extend_from_slice(&compressed_block);
+    }
+    result.extend_from_slice(&END);
+    Ok(result)
+}
+
+/// Returns the decompressed bytes of a frame or `DecompressError`, decompressing the blocks in parallel.
+///
+/// The frame can be created by [`compress`] or [`compress_parallel`].
+/// If multiple blocks are invalid, the error of any of them may be returned.
+///
+/// # Example
+/// ```
+/// use utf_c::frame;
+///
+/// let text = "Hello Wörld ".repeat(1024);
+/// let compressed_bytes = frame::compress(&text, 1024).unwrap();
+/// assert_eq!(frame::decompress_parallel(compressed_bytes).unwrap(), text.as_bytes());
+/// ```
+#[cfg(feature = "rayon")]
+pub fn decompress_parallel<T>(bytes: T) -> Result<Vec<u8>, DecompressError>
+where
+    T: AsRef<[u8]>,
+{
+    use rayon::prelude::*;
+
+    // The block headers are read first, so that the frame is checked before decompressing any block.
+    // The lengths are limited to what the blocks can decompress to, an invalid length is found by the decompression.
+    let blocks = blocks(bytes.as_ref())?.map(|block| {
+        let block = block?;
+        Ok((block, crate::capacity(block.bytes, block.decompressed_len()?)))
+    }).collect::<Result<Vec<_>, DecompressError>>()?;
+
+    // Each block is decompressed into its own part of the result.
+    let mut result = alloc::vec![0; blocks.iter().map(|(_, len)| len).sum()];
+    let mut rest = result.as_mut_slice();
+    let parts = blocks.into_iter().map(|(block, len)| {
+        let (part, next) = core::mem::take(&mut rest).split_at_mut(len);
+        rest = next;
+        (block, part)
+    }).collect::<Vec<_>>();
+
+    parts.into_par_iter().try_for_each(|(block, part)| block.decompress_into_slice(part).map(|_| ()))?;
+    Ok(result)
+}
+
+/// Returns an iterator over the blocks of a frame or `DecompressError`, if the frame header is invalid.
+///
+/// The blocks are not decompressed, so e.g. only the needed blocks can be decompressed.
+///
+/// # Example
+/// ```
+/// use utf_c::frame;
+///
+/// let compressed_bytes = frame::compress("Hello Wörld", 6).unwrap();
+/// let blocks = frame::blocks(&compressed_bytes).unwrap();
+/// assert_eq!(blocks.block_len(), 6);
+///
+/// let texts = blocks.map(|block| block?.decompress()).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(texts, ["Hello ".as_bytes(), "Wörld".as_bytes()]);
+/// ```
+pub fn blocks(bytes: &[u8]) -> Result<Blocks<'_>, DecompressError> {
+    let Some(header) = bytes.get(..HEADER_LEN) else {
+        return Err(if MAGIC.starts_with(bytes) { DecompressError::MissingBytes } else { DecompressError::MissingContainer });
+    };
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(DecompressError::MissingContainer);
+    }
+    if header[MAGIC.len()] != VERSION {
+        return Err(DecompressError::UnsupportedVersion { version: header[MAGIC.len()] });
+    }
+
+    // We can use `unwrap` here because the block length has exactly 4 bytes.
+    let block_len = u32::from_le_bytes(header[(MAGIC.len() + 1)..].try_into().unwrap()) as usize;
+    if block_len == 0 {
+        return Err(DecompressError::InvalidBlockLength);
+    }
+    Ok(Blocks { bytes, offset: HEADER_LEN, block_len, is_end: false })
+}
+
+/// An iterator over the blocks of a frame, see [`blocks`].
+///
+/// A block that decompresses to more than [`Blocks::block_len`] bytes is an error.
+/// After the end of the frame or an error, no more blocks are returned.
+#[derive(Clone)]
+pub struct Blocks<'a> {
+    bytes: &'a [u8],
+    /// The offset of the next block header.
+    offset: usize,
+    block_len: usize,
+    is_end: bool,
+}
+
+impl Blocks<'_> {
+    /// Returns the maximum length of the text of a block.
+    #[inline]
+    pub fn block_len(&self) -> usize {
+        self.block_len
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<Block<'a>, DecompressError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_end {
+            return None;
+        }
+
+        // All following returns end the iterator, except for a block.
+        self.is_end = true;
+        let rest = &self.bytes[self.offset..];
+        let Some(header) = rest.get(..BLOCK_HEADER_LEN) else {
+            return Some(Err(DecompressError::MissingBytes));
+        };
+
+        // We can use `unwrap` here because the header has exactly 4 bytes.
+        let compressed_len = u32::from_le_bytes(header.try_into().unwrap()) as usize;
+        if compressed_len == 0 {
+            let end = self.offset + BLOCK_HEADER_LEN;
+            if end < self.bytes.len() {
+                return Some(Err(DecompressError::TrailingData { at: end }));
+            }
+            return None;
+        }
+
+        let Some(bytes) = rest[BLOCK_HEADER_LEN..].get(..compressed_len) else {
+            return Some(Err(DecompressError::MissingBytes));
+        };
+        let block = Block { offset: self.offset + BLOCK_HEADER_LEN, bytes };
+
+        // Only the length header is read here, the decompression checks that the block has this length.
+        if let Ok(len) = block.decompressed_len() && len > self.block_len {
+            return Some(Err(DecompressError::BlockTooLong { at: block.offset, len }));
+        }
+        self.offset = block.offset + compressed_len;
+        self.is_end = false;
+        Some(Ok(block))
+    }
+}
+
+/// A compressed block of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Block<'a> {
+    offset: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    /// Returns the offset of the compressed block in the frame.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the compressed block (with the length header).
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Returns the length of the decompressed block or `DecompressError`.
+    #[inline]
+    pub fn decompressed_len(&self) -> Result<usize, DecompressError> {
+        crate::decompressed_len(self.bytes)
+    }
+
+    /// Returns the decompressed block or `DecompressError`.
+    ///
+    /// The offsets of the errors are offsets in the frame.
+    pub fn decompress(&self) -> Result<Vec<u8>, DecompressError> {
+        let mut result = Vec::new();
+        self.decompress_into(&mut result)?;
+        Ok(result)
+    }
+
+    /// Appends the decompressed block to `result` or returns `DecompressError`.
+    ///
+    /// If an error occurs, `result` is left unchanged.
+    pub fn decompress_into(&self, result: &mut Vec<u8>) -> Result<(), DecompressError> {
+        crate::decompress_into(self.bytes, result).map_err(|err| err.with_offset(self.offset))
+    }
+
+    /// Writes the decompressed block to `result` and returns its length or `DecompressError`.
+    ///
+    /// If `result` is too small, `DecompressError::BufferTooSmall` contains the required length.
+    pub fn decompress_into_slice(&self, result: &mut [u8]) -> Result<usize, DecompressError> {
+        crate::decompress_into_slice(self.bytes, result).map_err(|err| err.with_offset(self.offset))
+    }
+}
+
+/// Returns the blocks of the text, each with at most `block_len` bytes and ending at a character boundary.
+pub(crate) fn split(value: &[u8], block_len: usize) -> impl Iterator<Item = &[u8]> {
+    let mut rest = value;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        // `block_len` is at least the length of the longest character, so a block is never empty.
+        let len = if rest.len() <= block_len { rest.len() } else { utf8::complete_len(&rest[..block_len]) };
+        let (block, next) = rest.split_at(len);
+        rest = next;
+        Some(block)
+    })
+}
+
+/// Appends the frame header to `result`.
+#[inline]
+pub(crate) fn write_header(block_len: usize, result: &mut Vec<u8>) {
+    result.extend_from_slice(&MAGIC);
+    result.push(VERSION);
+    result.extend_from_slice(&(block_len as u32).to_le_bytes());
+}
+
+/// Appends the block header and the compressed block to `result` or returns `CompressError`.
+///
+/// If an error occurs, `result` is left unchanged.
+#[inline]
+pub(crate) fn compress_block(block: &[u8], result: &mut Vec<u8>) -> Result<(), CompressError> {
+    let header_idx = result.len();
+    result.extend_from_slice(&END);
+    if let Err(err) = crate::compress_into(block, result) {
+        result.truncate(header_idx);
+        return Err(err);
+    }
+
+    let compressed_len = (result.len() - header_idx - BLOCK_HEADER_LEN) as u32;
+    result[header_idx..(header_idx + BLOCK_HEADER_LEN)].copy_from_slice(&compressed_len.to_le_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn frame() {
+        let text = "Hello עוֹלָם 👋 ".repeat(32);
+        for block_len in [1, 4, 5, 7, 64, super::DEFAULT_BLOCK_LEN] {
+            let compressed_bytes = super::compress(&text, block_len).unwrap();
+            assert_eq!(super::decompress(&compressed_bytes).as_deref(), Ok(text.as_bytes()), "failed with block length {}", block_len);
+
+            // Each block is a valid UTF-8 text, which can be decompressed on its own.
+            let blocks = super::blocks(&compressed_bytes).unwrap();
+            assert_eq!(blocks.block_len(), block_len.max(super::MIN_BLOCK_LEN));
+            for block in blocks {
+                let block = block.unwrap();
+                let block_text = String::from_utf8(block.decompress().unwrap()).unwrap();
+                assert!(block_text.len() <= block_len.max(super::MIN_BLOCK_LEN));
+                assert_eq!(block.decompressed_len(), Ok(block_text.len()));
+            }
+        }
+
+        // An empty text has no blocks.
+        let compressed_bytes = super::compress("", super::DEFAULT_BLOCK_LEN).unwrap();
+        assert_eq!(compressed_bytes, [0, b'U', b'F', 1, 0, 0, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(super::decompress(&compressed_bytes), Ok(Vec::new()));
+
+        // The offsets of the errors are offsets in the text.
+        let err = super::compress(b"Hello W\x80rld", 4).unwrap_err();
+        assert_eq!(err, crate::CompressError::InvalidOrMissingPrefix { at: 7, bytes: vec![0x80] });
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        let text = "Hello עוֹלָם 👋 ".repeat(1024);
+        for block_len in [1, 7, 64, super::DEFAULT_BLOCK_LEN] {
+            let compressed_bytes = super::compress_parallel(&text, block_len).unwrap();
+            assert_eq!(compressed_bytes, super::compress(&text, block_len).unwrap(), "failed with block length {}", block_len);
+            assert_eq!(super::decompress_parallel(&compressed_bytes).as_deref(), Ok(text.as_bytes()), "failed with block length {}", block_len);
+        }
+
+        let compressed_bytes = super::compress_parallel("", super::DEFAULT_BLOCK_LEN).unwrap();
+        assert_eq!(super::decompress_parallel(&compressed_bytes), Ok(Vec::new()));
+
+        let err = super::compress_parallel(b"Hello W\x80rld", 4).unwrap_err();
+        assert_eq!(err, crate::CompressError::InvalidOrMissingPrefix { at: 7, bytes: vec![0x80] });
+
+        let compressed_bytes = super::compress(&text, 64).unwrap();
+        let frame_len = compressed_bytes.len();
+        assert_eq!(super::decompress_parallel(&compressed_bytes[..(frame_len - 1)]), Err(crate::DecompressError::MissingBytes));
+
+        // The errors of the blocks are the same as with `decompress`.
+        let mut invalid_bytes = super::compress("Hello Wörld", 6).unwrap();
+        invalid_bytes[8 + 4 + 7 + 4 + 2] = 0x80;
+        assert_eq!(super::decompress_parallel(&invalid_bytes), super::decompress(&invalid_bytes));
+        let mut invalid_bytes = super::compress("Hello Wörld", 6).unwrap();
+        invalid_bytes[8 + 4] = 255;
+        assert_eq!(super::decompress_parallel(&invalid_bytes), super::decompress(&invalid_bytes));
+    }
+
+    #[test]
+    fn decompress_invalid_frame() {
+        use crate::DecompressError;
+
+        let compressed_bytes = super::compress("Hello Wörld", 6).unwrap();
+        let frame_len = compressed_bytes.len();
+        assert_eq!(super::decompress(&compressed_bytes[..2]), Err(DecompressError::MissingBytes));
+        assert_eq!(super::decompress(&compressed_bytes[1..]), Err(DecompressError::MissingContainer));
+        assert_eq!(super::decompress(&compressed_bytes[..(frame_len - 1)]), Err(DecompressError::MissingBytes));
+        assert_eq!(super::decompress([compressed_bytes.as_slice(), &[0]].concat()), Err(DecompressError::TrailingData { at: frame_len }));
+
+        let mut invalid_bytes = compressed_bytes.clone();
+        invalid_bytes[3] = 2;
+        assert_eq!(super::decompress(&invalid_bytes), Err(DecompressError::UnsupportedVersion { version: 2 }));
+
+        let mut invalid_bytes = compressed_bytes.clone();
+        invalid_bytes[4..8].copy_from_slice(&[0; 4]);
+        assert_eq!(super::decompress(&invalid_bytes), Err(DecompressError::InvalidBlockLength));
+
+        // The first block has 6 bytes ("Hello ").
+        let mut invalid_bytes = compressed_bytes.clone();
+        invalid_bytes[4..8].copy_from_slice(&5u32.to_le_bytes());
+        assert_eq!(super::decompress(&invalid_bytes), Err(DecompressError::BlockTooLong { at: 12, len: 6 }));
+        assert_eq!(super::blocks(&invalid_bytes).unwrap().next(), Some(Err(DecompressError::BlockTooLong { at: 12, len: 6 })));
+
+        // The second block starts after the frame header, the first block and the second block header.
+        let mut invalid_bytes = compressed_bytes;
+        let second_block_idx = 8 + 4 + 7 + 4;
+        invalid_bytes[second_block_idx + 2] = 0x80;
+        assert_eq!(super::decompress(&invalid_bytes), Err(DecompressError::MissingPrefix { at: second_block_idx + 2, bytes: vec![0x80, 182, 114, 108] }));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{buffer::Buffer, DecompressError, LengthHeader, Options};
+
+/// The first bytes of the container header.
+///
+/// A length header of UTF-C 1.0 never starts with `0x00`, because the input is never empty,
+/// so the container header can't be confused with it.
+pub(crate) const MAGIC: [u8; 3] = [0x00, b'U', b'C'];
+
+/// The version of the container format.
+pub(crate) const VERSION: u8 = 1;
+
+/// The length of the container header (magic, version and flags).
+pub(crate) const CONTAINER_LEN: usize = MAGIC.len() + 2;
+
+/* container flags */
+/// The length header is a varint ([`LengthHeader::Varint`]).
+pub(crate) const FLAG_VARINT: u8 = 0b0000_0001;
+/// A CRC-32C of the decompressed bytes follows the compressed bytes ([`Options::checksum`]).
+pub(crate) const FLAG_CHECKSUM: u8 = 0b0000_0010;
+// 0b0000_0100 is reserved for the ID of a dictionary.
+/// The last prefixes are remembered ([`Options::prefix_cache`]).
+pub(crate) const FLAG_PREFIX_CACHE: u8 = 0b0000_1000;
+/// CJK, Kana and Hangul characters are encoded in a window ([`Options::wide_windows`]).
+pub(crate) const FLAG_WIDE_WINDOWS: u8 = 0b0001_0000;
+/// The characters of small alphabets are encoded in a window ([`Options::narrow_windows`]).
+pub(crate) const FLAG_NARROW_WINDOWS: u8 = 0b0010_0000;
+/// Emoji sequences are encoded with shorter codes ([`Options::emoji`]).
+pub(crate) const FLAG_EMOJI: u8 = 0b0100_0000;
+/// All flags that are supported by this version.
+const SUPPORTED_FLAGS: u8 = FLAG_VARINT | FLAG_CHECKSUM | FLAG_PREFIX_CACHE | FLAG_WIDE_WINDOWS | FLAG_NARROW_WINDOWS | FLAG_EMOJI;
+
+/// The maximum length of a varint length header (enough for a 64-bit `usize`).
+pub(crate) const MAX_VARINT_LEN: usize = 10;
+
+/// Writes the container header for the given `options`.
+pub(crate) fn write_container<B: Buffer>(options: &Options, result: &mut B) {
+    let mut flags = 0;
+    if options.get_length_header() == LengthHeader::Varint {
+        flags |= FLAG_VARINT;
+    }
+    if options.get_checksum() {
+        flags |= FLAG_CHECKSUM;
+    }
+    if options.get_prefix_cache() {
+        flags |= FLAG_PREFIX_CACHE;
+    }
+    if options.get_wide_windows() {
+        flags |= FLAG_WIDE_WINDOWS;
+    }
+    if options.get_narrow_windows() {
+        flags |= FLAG_NARROW_WINDOWS;
+    }
+    if options.get_emoji() {
+        flags |= FLAG_EMOJI;
+    }
+
+    result.extend_from_slice(&MAGIC);
+    result.push(VERSION);
+    result.push(flags);
+}
+
+/// Returns `true` if `value` starts with the magic bytes of the container header.
+#[inline]
+pub(crate) fn has_container(value: &[u8]) -> bool {
+    value.starts_with(&MAGIC)
+}
+
+/// Returns the options stored in the container header and the remaining bytes or `DecompressError`.
+pub(crate) fn read_container(value: &[u8]) -> Result<(Options, &[u8]), DecompressError> {
+    if !has_container(value) {
+        return Err(DecompressError::MissingContainer);
+    }
+    let (version, flags) = match value.get(MAGIC.len()..CONTAINER_LEN) {
+        Some(&[version, flags]) => (version, flags),
+        _ => return Err(DecompressError::MissingBytes),
+    };
+
+    if version != VERSION {
+        return Err(DecompressError::UnsupportedVersion { version });
+    }
+    if flags & !SUPPORTED_FLAGS != 0 {
+        return Err(DecompressError::UnsupportedFlags { flags });
+    }
+
+    let length_header = if flags & FLAG_VARINT != 0 { LengthHeader::Varint } else { LengthHeader::Unary };
+    let options = Options::new()
+        .container(true)
+        .length_header(length_header)
+        .checksum(flags & FLAG_CHECKSUM != 0)
+        .prefix_cache(flags & FLAG_PREFIX_CACHE != 0)
+        .wide_windows(flags & FLAG_WIDE_WINDOWS != 0)
+        .narrow_windows(flags & FLAG_NARROW_WINDOWS != 0)
+        .emoji(flags & FLAG_EMOJI != 0);
+    Ok((options, &value[CONTAINER_LEN..]))
+}
+
+/// Returns the length of the length header for `data_len` bytes.
+pub(crate) const fn len(length_header: LengthHeader, data_len: usize) -> usize {
+    match length_header {
+        LengthHeader::Unary => (data_len / 255) + 1,
+        LengthHeader::Varint => {
+            // Each byte holds 7 bits, and at least one byte is always written.
+            let bits = usize::BITS - (data_len | 1).leading_zeros();
+            bits.div_ceil(7) as usize
+        },
+    }
+}
+
+/// Writes the length header for `data_len` bytes.
+pub(crate) fn write<B: Buffer>(length_header: LengthHeader, data_len: usize, result: &mut B) {
+    match length_header {
+        LengthHeader::Unary => {
+            let data_len_count = data_len / 255;
+            let data_len_remainder = data_len % 255;
+
+            if data_len_count > 0 {
+                result.fill(255, data_len_count);
+            }
+            result.push(data_len_remainder as u8);
+        },
+        LengthHeader::Varint => {
+            let mut data_len = data_len;
+            while data_len >= 0x80 {
+                // The highest bit marks that another byte follows.
+                result.push((data_len as u8) | 0x80);
+                data_len >>= 7;
+            }
+            result.push(data_len as u8);
+        },
+    }
+}
+
+/// Returns the length stored in the header and the remaining bytes or `DecompressError`.
+pub(crate) fn read(length_header: LengthHeader, value: &[u8]) -> Result<(usize, &[u8]), DecompressError> {
+    if value.len() < 2 {
+        return Err(DecompressError::InvalidLength);
+    }
+
+    match length_header {
+        LengthHeader::Unary => read_unary(value),
+        LengthHeader::Varint => read_varint(value),
+    }
+}
+
+fn read_unary(value: &[u8]) -> Result<(usize, &[u8]), DecompressError> {
+    // Each previous byte has the value 255.
+    let idx = value.iter().position(|&byte| byte < 255).ok_or(DecompressError::MissingBytes)?;
+    let data_len = (idx * 255) + value[idx] as usize;
+    Ok((data_len, &value[(idx + 1)..]))
+}
+
+fn read_varint(value: &[u8]) -> Result<(usize, &[u8]), DecompressError> {
+    let mut data_len = 0usize;
+    for (idx, &byte) in value.iter().take(MAX_VARINT_LEN).enumerate() {
+        let bits = (byte & 0x7F) as usize;
+        let shift = (idx * 7) as u32;
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            // The length does not fit into `usize`.
+            return Err(DecompressError::LengthOverflow);
+        }
+
+        data_len |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok((data_len, &value[(idx + 1)..]));
+        }
+    }
+
+    if value.len() >= MAX_VARINT_LEN {
+        return Err(DecompressError::LengthOverflow);
+    }
+    Err(DecompressError::MissingBytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LengthHeader;
+
+    #[test]
+    fn container() {
+        let options = crate::Options::new().container(true).length_header(LengthHeader::Varint).checksum(true);
+        let mut bytes = Vec::new();
+        super::write_container(&options, &mut bytes);
+        assert_eq!(bytes, [0x00, b'U', b'C', super::VERSION, super::FLAG_VARINT | super::FLAG_CHECKSUM]);
+
+        bytes.push(1);
+        assert_eq!(super::read_container(&bytes), Ok((options, [1].as_slice())));
+
+        let options = crate::Options::new().container(true).prefix_cache(true).wide_windows(true).narrow_windows(true).emoji(true);
+        let mut bytes = Vec::new();
+        super::write_container(&options, &mut bytes);
+        assert_eq!(bytes, [0x00, b'U', b'C', super::VERSION, super::FLAG_PREFIX_CACHE | super::FLAG_WIDE_WINDOWS | super::FLAG_NARROW_WINDOWS | super::FLAG_EMOJI]);
+        assert_eq!(super::read_container(&bytes), Ok((options, [].as_slice())));
+
+        assert_eq!(super::read_container(&[1, 72]), Err(crate::DecompressError::MissingContainer));
+        assert_eq!(super::read_container(&[0x00, b'U', b'C', super::VERSION]), Err(crate::DecompressError::MissingBytes));
+        assert_eq!(super::read_container(&[0x00, b'U', b'C', 2, 0]), Err(crate::DecompressError::UnsupportedVersion { version: 2 }));
+        assert_eq!(super::read_container(&[0x00, b'U', b'C', super::VERSION, 0b1000_0000]), Err(crate::DecompressError::UnsupportedFlags { flags: 0b1000_0000 }));
+    }
+
+    #[test]
+    fn varint() {
+        let test_cases: [(usize, &[u8]); 6] = [
+            (1,          &[1]),
+            (127,        &[127]),
+            (128,        &[128, 1]),
+            (300,        &[172, 2]),
+            (1 << 20,    &[128, 128, 64]),
+            (usize::MAX, &[255, 255, 255, 255, 255, 255, 255, 255, 255, 1]),
+        ];
+
+        for (data_len, header) in test_cases {
+            let mut bytes = Vec::new();
+            super::write(LengthHeader::Varint, data_len, &mut bytes);
+            assert_eq!(bytes, header, "Header does not match for: {}", data_len);
+            assert_eq!(super::len(LengthHeader::Varint, data_len), header.len(), "Header length does not match for: {}", data_len);
+
+            bytes.push(72);
+            assert_eq!(super::read(LengthHeader::Varint, &bytes), Ok((data_len, [72].as_slice())), "Reading failed for: {}", data_len);
+        }
+
+        assert_eq!(super::read(LengthHeader::Varint, &[128, 128]), Err(crate::DecompressError::MissingBytes));
+        assert_eq!(super::read(LengthHeader::Varint, &[255; 11]), Err(crate::DecompressError::LengthOverflow));
+        assert_eq!(super::read(LengthHeader::Varint, &[255, 255, 255, 255, 255, 255, 255, 255, 255, 2, 72]), Err(crate::DecompressError::LengthOverflow));
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(feature = "simd_l1")]
 mod simd;
 
+use crate::{buffer::Buffer, utf8};
+
 /// This function uses SIMD (if the feature is enabled, otherwise a normal loop is used) to find a non-ASCII character 
 /// and returns `true` if one is found, otherwise `false`.
 /// 
@@ -77,6 +79,94 @@ pub(crate) fn find_pos_byte_idx(bytes: &[u8]) -> Option<usize> {
     return bytes.iter().position(|b| test_sign_bit(*b));
 }
 
+/// This function uses SIMD (if the feature is enabled, otherwise a normal loop is used) to compress a run of 2-byte characters
+/// that start with `lead` (the prefix), by writing only their second byte.
+/// 
+/// Returns the number of read bytes (2 per character), which is `0` if the first character is not part of the run.
+#[inline]
+pub(crate) fn compress_prefix_run<B: Buffer>(bytes: &[u8], lead: u8, result: &mut B) -> usize {
+    /// The number of characters that are checked without SIMD first, because most runs are short (e.g. in Cyrillic).
+    const MIN_SIMD_RUN: usize = 4;
+
+    let mut idx = 0;
+    while let [first, second, ..] = bytes[idx..] && first == lead && utf8::is_continuation(second) {
+        result.push(second);
+        idx += 2;
+        if idx == MIN_SIMD_RUN * 2 {
+            break;
+        }
+    }
+    if idx < MIN_SIMD_RUN * 2 {
+        return idx;
+    }
+
+    #[cfg(feature = "simd_l1")]
+    {
+        let len = bytes.len() - idx;
+        let mut cpr = simd::CompressPrefixRun::from((bytes, lead, &mut idx));
+
+        #[cfg(feature = "simd_l2")]
+        if len >= simd::CompressPrefixRun::VEC_LEN_LEVEL2 && unsafe { cpr.level2(result) } {
+            return idx;
+        }
+
+        if len >= simd::CompressPrefixRun::VEC_LEN_LEVEL1 && unsafe { cpr.level1(result) } {
+            return idx;
+        }
+    }
+
+    // Now check the remaining characters.
+    while let [first, second, ..] = bytes[idx..] && first == lead && utf8::is_continuation(second) {
+        result.push(second);
+        idx += 2;
+    }
+    idx
+}
+
+/// This function uses SIMD (if the feature is enabled, otherwise a normal loop is used) to decompress a run of 2-byte characters
+/// that start with `lead` (the prefix), by writing the prefix before each second byte.
+/// 
+/// Returns the number of read bytes (1 per character), which is `0` if the first byte is not a continuation byte.
+#[inline]
+pub(crate) fn decompress_prefix_run<B: Buffer>(bytes: &[u8], lead: u8, result: &mut B) -> usize {
+    /// The number of characters that are checked without SIMD first, because most runs are short.
+    const MIN_SIMD_RUN: usize = 4;
+
+    let mut idx = 0;
+    while let [utf8_char, ..] = bytes[idx..] && utf8::is_continuation(utf8_char) {
+        result.extend_from_slice(&[lead, utf8_char]);
+        idx += 1;
+        if idx == MIN_SIMD_RUN {
+            break;
+        }
+    }
+    if idx < MIN_SIMD_RUN {
+        return idx;
+    }
+
+    #[cfg(feature = "simd_l1")]
+    {
+        let len = bytes.len() - idx;
+        let mut dpr = simd::DecompressPrefixRun::from((bytes, lead, &mut idx));
+
+        #[cfg(feature = "simd_l2")]
+        if len >= simd::DecompressPrefixRun::VEC_LEN_LEVEL2 && unsafe { dpr.level2(result) } {
+            return idx;
+        }
+
+        if len >= simd::DecompressPrefixRun::VEC_LEN_LEVEL1 && unsafe { dpr.level1(result) } {
+            return idx;
+        }
+    }
+
+    // Now check the remaining characters (this is also the reference for the SIMD instructions).
+    while let [utf8_char, ..] = bytes[idx..] && utf8::is_continuation(utf8_char) {
+        result.extend_from_slice(&[lead, utf8_char]);
+        idx += 1;
+    }
+    idx
+}
+
 /// Returns `true` if the sign bit is set, otherwise `false`.
 #[inline(always)]
 pub(crate) const fn test_sign_bit(byte: u8) -> bool {
@@ -112,6 +202,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compress_prefix_run() {
+        // The same characters with and without SIMD (at the beginning, in the middle and at the end of a vector).
+        for run_len in [0, 1, 7, 8, 9, 16, 17, 40] {
+            let mut bytes = "ж".repeat(run_len).into_bytes();
+            bytes.extend_from_slice("я Hello Wörld".as_bytes());
+            bytes.extend_from_slice(&[0; 32]);
+
+            let mut result = Vec::new();
+            let read = super::compress_prefix_run(&bytes, 208, &mut result);
+            assert_eq!(read, run_len * 2, "failed with run length {}", run_len);
+            assert_eq!(result, [182].repeat(run_len), "failed with run length {}", run_len);
+        }
+
+        // Only the second byte has to be a continuation byte.
+        let mut result = Vec::new();
+        assert_eq!(super::compress_prefix_run(&[208, 182, 208, 72, 208, 182], 208, &mut result), 2);
+        assert_eq!(result, [182]);
+    }
+
+    #[test]
+    fn decompress_prefix_run() {
+        // The same characters with and without SIMD (at the beginning, in the middle and at the end of a vector).
+        for run_len in [0, 1, 4, 5, 15, 16, 17, 33, 70] {
+            let mut bytes = (0..run_len).map(|idx| 128 + (idx % 64) as u8).collect::<Vec<u8>>();
+            bytes.extend_from_slice("Hello Wörld".as_bytes());
+            bytes.extend_from_slice(&[0; 32]);
+
+            let mut result = Vec::new();
+            let read = super::decompress_prefix_run(&bytes, 208, &mut result);
+            let chars = bytes[..run_len].iter().flat_map(|&utf8_char| [208, utf8_char]).collect::<Vec<u8>>();
+            assert_eq!(read, run_len, "failed with run length {}", run_len);
+            assert_eq!(result, chars, "failed with run length {}", run_len);
+        }
+    }
+
     #[test]
     fn find_pos_byte_idx() {
         let test_cases: [(&[u8], usize); 11] = [
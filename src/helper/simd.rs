@@ -1,10 +1,12 @@
 #[cfg(target_arch = "x86")]
-use std::arch::x86;
+use core::arch::x86;
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64 as x86;
+use core::arch::x86_64 as x86;
 #[cfg(any(target_arch = "aarch64", target_arch = "arm64ec"))]
 use core::arch::aarch64 as arm;
 
+use crate::buffer::Buffer;
+
 #[cfg(not(any(
     target_arch = "x86", target_arch = "x86_64",
     target_arch = "aarch64", target_arch = "arm64ec",
@@ -121,6 +123,229 @@ impl<'a, 'b> From<(&'a [u8], &'b mut usize)> for FindPositiveByteIndex<'a, 'b> {
     }
 }
 
+/// The maximum number of characters per vector (for the buffer of the characters).
+const MAX_RUN_CHARS: usize = 16;
+
+pub struct CompressPrefixRun<'a, 'b> {
+    bytes: &'a [u8],
+    /// The first byte of the characters (the prefix).
+    lead: u8,
+    index: &'b mut usize,
+}
+
+impl CompressPrefixRun<'_, '_> {
+    #[cfg(target_feature = "sse2")]
+    pub const VEC_LEN_LEVEL1: usize = 16;
+    // NEON loads two vectors at once (one for the first and one for the second bytes).
+    #[cfg(target_feature = "neon")]
+    pub const VEC_LEN_LEVEL1: usize = 32;
+    #[cfg(feature = "simd_l2")]
+    pub const VEC_LEN_LEVEL2: usize = 32;
+
+    /// Writes the second bytes of the characters of each vector and returns `true` if the run has ended, otherwise `false`.
+    fn r#loop<B, F>(&mut self, vec_len: usize, result: &mut B, chars_cb: F) -> bool 
+    where 
+        B: Buffer,
+        F: Fn(&usize, *const u8, &mut [u8; MAX_RUN_CHARS]) -> usize,
+    {
+        let (len, ptr) = (self.bytes.len(), self.bytes.as_ptr());
+        let mut chars = [0; MAX_RUN_CHARS];
+
+        while *self.index + vec_len <= len {
+            let chars_len = chars_cb(self.index, ptr, &mut chars);
+            result.extend_from_slice(&chars[..chars_len]);
+            *self.index += chars_len * 2;
+
+            if chars_len < vec_len / 2 {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub unsafe fn level1<B: Buffer>(&mut self, result: &mut B) -> bool {
+        if !feature_detected!(level1) {
+            return false;
+        }
+
+        let lead = self.lead;
+        self.r#loop(Self::VEC_LEN_LEVEL1, result, |&idx, ptr, chars| {
+            #[cfg(target_feature = "sse2")]
+            unsafe {
+                let simd_vec = x86::_mm_loadu_si128(ptr.add(idx) as *const x86::__m128i);
+                let lead_mask = x86::_mm_movemask_epi8(x86::_mm_cmpeq_epi8(simd_vec, x86::_mm_set1_epi8(lead as i8))) as u32;
+                let continuation_vec = x86::_mm_and_si128(simd_vec, x86::_mm_set1_epi8(0b11000000_u8 as i8));
+                let continuation_mask = x86::_mm_movemask_epi8(x86::_mm_cmpeq_epi8(continuation_vec, x86::_mm_set1_epi8(0b10000000_u8 as i8))) as u32;
+
+                // The second bytes are moved to the lower 8 bytes.
+                let chars_vec = x86::_mm_packus_epi16(x86::_mm_srli_epi16(simd_vec, 8), x86::_mm_setzero_si128());
+                x86::_mm_storel_epi64(chars.as_mut_ptr() as *mut x86::__m128i, chars_vec);
+
+                // A character is part of the run if the first byte (even bit) and the second byte (odd bit) match.
+                let mask = (lead_mask & 0x5555) | (continuation_mask & 0xAAAA);
+                ((!mask).trailing_zeros() / 2) as usize
+            }
+
+            #[cfg(target_feature = "neon")]
+            unsafe {
+                let simd_vecs = arm::vld2q_u8(ptr.add(idx));
+                let lead_vec = arm::vceqq_u8(simd_vecs.0, arm::vdupq_n_u8(lead));
+                let continuation_vec = arm::vceqq_u8(arm::vandq_u8(simd_vecs.1, arm::vdupq_n_u8(0b11000000)), arm::vdupq_n_u8(0b10000000));
+                arm::vst1q_u8(chars.as_mut_ptr(), simd_vecs.1);
+
+                let mask = neon_movemask_epu8(arm::vandq_u8(lead_vec, continuation_vec)) as u32;
+                (!mask).trailing_zeros() as usize
+            }
+        })
+    }
+
+    #[cfg(feature = "simd_l2")]
+    pub unsafe fn level2<B: Buffer>(&mut self, result: &mut B) -> bool {
+        #[cfg(not(target_feature = "avx2"))]
+        compile_error!("A required SIMD instruction for your processor is missing. Please disable the \"simd_l2\" feature for \"utf-c\"!");
+
+        if !feature_detected!(level2) {
+            return false;
+        }
+
+        let lead = self.lead;
+        self.r#loop(Self::VEC_LEN_LEVEL2, result, |&idx, ptr, chars| {
+            #[cfg(feature = "simd_l2")]
+            unsafe {
+                let simd_vec = x86::_mm256_loadu_si256(ptr.add(idx) as *const x86::__m256i);
+                let lead_mask = x86::_mm256_movemask_epi8(x86::_mm256_cmpeq_epi8(simd_vec, x86::_mm256_set1_epi8(lead as i8))) as u32;
+                let continuation_vec = x86::_mm256_and_si256(simd_vec, x86::_mm256_set1_epi8(0b11000000_u8 as i8));
+                let continuation_mask = x86::_mm256_movemask_epi8(x86::_mm256_cmpeq_epi8(continuation_vec, x86::_mm256_set1_epi8(0b10000000_u8 as i8))) as u32;
+
+                // The second bytes are packed per lane, so the lower 8 bytes of both lanes are moved together.
+                let chars_vec = x86::_mm256_packus_epi16(x86::_mm256_srli_epi16(simd_vec, 8), x86::_mm256_setzero_si256());
+                let chars_vec = x86::_mm256_permute4x64_epi64(chars_vec, 0b11_01_10_00);
+                x86::_mm_storeu_si128(chars.as_mut_ptr() as *mut x86::__m128i, x86::_mm256_castsi256_si128(chars_vec));
+
+                let mask = (lead_mask & 0x5555_5555) | (continuation_mask & 0xAAAA_AAAA);
+                ((!mask).trailing_zeros() / 2) as usize
+            }
+        })
+    }
+}
+
+impl<'a, 'b> From<(&'a [u8], u8, &'b mut usize)> for CompressPrefixRun<'a, 'b> {
+    #[inline]
+    fn from(value: (&'a [u8], u8, &'b mut usize)) -> Self {
+        Self { bytes: value.0, lead: value.1, index: value.2 }
+    }
+}
+
+/// The maximum number of decompressed bytes per vector (for the buffer of the characters).
+const MAX_RUN_BYTES: usize = 64;
+
+pub struct DecompressPrefixRun<'a, 'b> {
+    bytes: &'a [u8],
+    /// The first byte of the characters (the prefix).
+    lead: u8,
+    index: &'b mut usize,
+}
+
+impl DecompressPrefixRun<'_, '_> {
+    pub const VEC_LEN_LEVEL1: usize = 16;
+    #[cfg(feature = "simd_l2")]
+    pub const VEC_LEN_LEVEL2: usize = 32;
+
+    /// Writes the characters (prefix and second byte) of each vector and returns `true` if the run has ended, otherwise `false`.
+    fn r#loop<B, F>(&mut self, vec_len: usize, result: &mut B, chars_cb: F) -> bool 
+    where 
+        B: Buffer,
+        F: Fn(&usize, *const u8, &mut [u8; MAX_RUN_BYTES]) -> usize,
+    {
+        let (len, ptr) = (self.bytes.len(), self.bytes.as_ptr());
+        let mut chars = [0; MAX_RUN_BYTES];
+
+        while *self.index + vec_len <= len {
+            let chars_len = chars_cb(self.index, ptr, &mut chars);
+            result.extend_from_slice(&chars[..(chars_len * 2)]);
+            *self.index += chars_len;
+
+            if chars_len < vec_len {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub unsafe fn level1<B: Buffer>(&mut self, result: &mut B) -> bool {
+        if !feature_detected!(level1) {
+            return false;
+        }
+
+        let lead = self.lead;
+        self.r#loop(Self::VEC_LEN_LEVEL1, result, |&idx, ptr, chars| {
+            #[cfg(target_feature = "sse2")]
+            unsafe {
+                let simd_vec = x86::_mm_loadu_si128(ptr.add(idx) as *const x86::__m128i);
+                let continuation_vec = x86::_mm_and_si128(simd_vec, x86::_mm_set1_epi8(0b11000000_u8 as i8));
+                let mask = x86::_mm_movemask_epi8(x86::_mm_cmpeq_epi8(continuation_vec, x86::_mm_set1_epi8(0b10000000_u8 as i8))) as u32;
+
+                // The prefix is interleaved with the second bytes (`lead c0 lead c1 ...`).
+                let lead_vec = x86::_mm_set1_epi8(lead as i8);
+                x86::_mm_storeu_si128(chars.as_mut_ptr() as *mut x86::__m128i, x86::_mm_unpacklo_epi8(lead_vec, simd_vec));
+                x86::_mm_storeu_si128(chars.as_mut_ptr().add(16) as *mut x86::__m128i, x86::_mm_unpackhi_epi8(lead_vec, simd_vec));
+
+                (!mask).trailing_zeros() as usize
+            }
+
+            #[cfg(target_feature = "neon")]
+            unsafe {
+                let simd_vec = arm::vld1q_u8(ptr.add(idx));
+                let continuation_vec = arm::vceqq_u8(arm::vandq_u8(simd_vec, arm::vdupq_n_u8(0b11000000)), arm::vdupq_n_u8(0b10000000));
+                let mask = neon_movemask_epu8(continuation_vec) as u32;
+
+                // The prefix is interleaved with the second bytes (`lead c0 lead c1 ...`).
+                arm::vst2q_u8(chars.as_mut_ptr(), arm::uint8x16x2_t(arm::vdupq_n_u8(lead), simd_vec));
+
+                (!mask).trailing_zeros() as usize
+            }
+        })
+    }
+
+    #[cfg(feature = "simd_l2")]
+    pub unsafe fn level2<B: Buffer>(&mut self, result: &mut B) -> bool {
+        #[cfg(not(target_feature = "avx2"))]
+        compile_error!("A required SIMD instruction for your processor is missing. Please disable the \"simd_l2\" feature for \"utf-c\"!");
+
+        if !feature_detected!(level2) {
+            return false;
+        }
+
+        let lead = self.lead;
+        self.r#loop(Self::VEC_LEN_LEVEL2, result, |&idx, ptr, chars| {
+            #[cfg(feature = "simd_l2")]
+            unsafe {
+                let simd_vec = x86::_mm256_loadu_si256(ptr.add(idx) as *const x86::__m256i);
+                let continuation_vec = x86::_mm256_and_si256(simd_vec, x86::_mm256_set1_epi8(0b11000000_u8 as i8));
+                let mask = x86::_mm256_movemask_epi8(x86::_mm256_cmpeq_epi8(continuation_vec, x86::_mm256_set1_epi8(0b10000000_u8 as i8))) as u32;
+
+                // Each second byte is extended to 16 bits and becomes the upper byte, after the prefix (little-endian).
+                let lead_vec = x86::_mm256_set1_epi16(lead as i16);
+                let low_vec = x86::_mm256_cvtepu8_epi16(x86::_mm256_castsi256_si128(simd_vec));
+                let high_vec = x86::_mm256_cvtepu8_epi16(x86::_mm256_extracti128_si256(simd_vec, 1));
+                x86::_mm256_storeu_si256(chars.as_mut_ptr() as *mut x86::__m256i, x86::_mm256_or_si256(x86::_mm256_slli_epi16(low_vec, 8), lead_vec));
+                x86::_mm256_storeu_si256(chars.as_mut_ptr().add(32) as *mut x86::__m256i, x86::_mm256_or_si256(x86::_mm256_slli_epi16(high_vec, 8), lead_vec));
+
+                (!mask).trailing_zeros() as usize
+            }
+        })
+    }
+}
+
+impl<'a, 'b> From<(&'a [u8], u8, &'b mut usize)> for DecompressPrefixRun<'a, 'b> {
+    #[inline]
+    fn from(value: (&'a [u8], u8, &'b mut usize)) -> Self {
+        Self { bytes: value.0, lead: value.1, index: value.2 }
+    }
+}
+
 #[cfg(target_feature = "neon")]
 /// An alternative to `_mm_movemask_epi8` (SSE2) for NEON.
 /// 
@@ -171,6 +396,63 @@ mod tests {
             (&[ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128 ], 31),
         ];
     
+    #[test]
+    fn cpr_run() {
+        // 20 characters `א` (D7 90) and `ב` (D7 91) with the same first byte, followed by ASCII characters.
+        let mut bytes = [215, 144].repeat(20);
+        bytes.extend_from_slice(&[215, 145]);
+        bytes.extend_from_slice(&[72; 32]);
+        let chars = [[144].repeat(20).as_slice(), &[145]].concat();
+
+        {
+            let (mut idx, mut result) = (0, Vec::new());
+            let mut cpr = super::CompressPrefixRun::from((bytes.as_slice(), 215, &mut idx));
+            let is_end = unsafe { cpr.level1(&mut result) };
+            assert_eq!((is_end, idx), (true, 42));
+            assert_eq!(result, chars);
+        }
+
+        #[cfg(feature = "simd_l2")]
+        {
+            let (mut idx, mut result) = (0, Vec::new());
+            let mut cpr = super::CompressPrefixRun::from((bytes.as_slice(), 215, &mut idx));
+            let is_end = unsafe { cpr.level2(&mut result) };
+            assert_eq!((is_end, idx), (true, 42));
+            assert_eq!(result, chars);
+        }
+
+        // The run ends at a character with another first byte.
+        let (mut idx, mut result) = (0, Vec::new());
+        let mut cpr = super::CompressPrefixRun::from((bytes.as_slice(), 214, &mut idx));
+        assert!(unsafe { cpr.level1(&mut result) });
+        assert_eq!((idx, result.len()), (0, 0));
+    }
+
+    #[test]
+    fn dpr_run() {
+        // 40 characters `א`-`ר` (D7 90-D7 A7) without the prefix, followed by ASCII characters.
+        let mut bytes = (0..40).map(|idx| 144 + (idx % 24)).collect::<Vec<u8>>();
+        bytes.extend_from_slice(&[72; 32]);
+        let chars = bytes[..40].iter().flat_map(|&utf8_char| [215, utf8_char]).collect::<Vec<u8>>();
+
+        {
+            let (mut idx, mut result) = (0, Vec::new());
+            let mut dpr = super::DecompressPrefixRun::from((bytes.as_slice(), 215, &mut idx));
+            let is_end = unsafe { dpr.level1(&mut result) };
+            assert_eq!((is_end, idx), (true, 40));
+            assert_eq!(result, chars);
+        }
+
+        #[cfg(feature = "simd_l2")]
+        {
+            let (mut idx, mut result) = (0, Vec::new());
+            let mut dpr = super::DecompressPrefixRun::from((bytes.as_slice(), 215, &mut idx));
+            let is_end = unsafe { dpr.level2(&mut result) };
+            assert_eq!((is_end, idx), (true, 40));
+            assert_eq!(result, chars);
+        }
+    }
+
     #[test]
     fn fpbi_search() {
         for (idx, result) in TEST_CASES.into_iter().enumerate() {
@@ -0,0 +1,276 @@
+//! Streaming compression and decompression with [`std::io`].
+//!
+//! The [`Encoder`] and [`Decoder`] use the same format as the [`stream`](crate::stream) module.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::stream::{Compressor, Decompressor, CHUNK_LEN};
+
+/// Converts a `CompressError` or `DecompressError` into an `io::Error`.
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// A streaming compressor that writes the compressed bytes to `W`.
+///
+/// The text can be written in arbitrary chunks, even if a character is split between them.
+///
+/// __IMPORTANT:__ Call [`Encoder::finish`] after the last write, otherwise the stream is incomplete.
+///
+/// # Example
+/// ```
+/// use std::io::Write;
+///
+/// let mut encoder = utf_c::io::Encoder::new(Vec::new());
+/// encoder.write_all("Hello ".as_bytes()).unwrap();
+/// encoder.write_all(&"Wörld".as_bytes()[..2]).unwrap();
+/// encoder.write_all(&"Wörld".as_bytes()[2..]).unwrap();
+/// let compressed_bytes = encoder.finish().unwrap();
+/// assert_eq!(compressed_bytes, [12, 0, 72, 101, 108, 108, 111, 32, 87, 195, 182, 114, 108, 100, 0, 0]);
+/// //                            |      |-Characters                                         |-End
+/// //                            |-Length
+/// ```
+pub struct Encoder<W: Write> {
+    writer: W,
+    compressor: Compressor,
+    /// The compressed bytes that have not been written yet.
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Creates a new encoder that writes to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            compressor: Compressor::new(),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// __NOTE:__ Writing directly to the underlying writer corrupts the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Writes the remaining characters and the end of the stream, and returns the underlying writer.
+    ///
+    /// Returns an error with `io::ErrorKind::InvalidData` if the last character is incomplete.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_buf()?;
+        self.compressor.finish(&mut self.buf).map_err(invalid_data)?;
+        self.write_buf()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Writes all compressed bytes to the underlying writer.
+    fn write_buf(&mut self) -> io::Result<()> {
+        while !self.buf.is_empty() {
+            match self.writer.write(&self.buf) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => drop(self.buf.drain(..len)),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write the bytes of the last call first, so that nothing is read if this fails.
+        self.write_buf()?;
+
+        // Limit the input, so that the compressed bytes don't need more memory than a chunk.
+        let buf = &buf[..buf.len().min(CHUNK_LEN)];
+        let progress = self.compressor.update(buf, &mut self.buf).map_err(invalid_data)?;
+        Ok(progress.read)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.compressor.flush(&mut self.buf);
+        self.write_buf()?;
+        self.writer.flush()
+    }
+}
+
+/// A streaming decompressor that reads the compressed bytes from `R`.
+///
+/// Only one chunk is kept in memory at a time, and each character is validated while decoding.
+///
+/// # Example
+/// ```
+/// use std::io::{BufRead, Write};
+///
+/// let mut encoder = utf_c::io::Encoder::new(Vec::new());
+/// encoder.write_all("Hello\nWörld".as_bytes()).unwrap();
+/// let compressed_bytes = encoder.finish().unwrap();
+///
+/// let decoder = utf_c::io::Decoder::new(compressed_bytes.as_slice());
+/// let lines = decoder.lines().collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(lines, ["Hello", "Wörld"]);
+/// ```
+pub struct Decoder<R: Read> {
+    reader: R,
+    decompressor: Decompressor,
+    /// The compressed bytes that were read from the underlying reader.
+    input: Vec<u8>,
+    /// The decompressed bytes and the position of the first unread byte.
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Creates a new decoder that reads from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decompressor: Decompressor::new(),
+            input: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// __NOTE:__ Reading directly from the underlying reader corrupts the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// If the end of the stream has been read, the reader is positioned directly after it.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads compressed bytes (but never more than the current header or chunk) and decompresses them.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        self.input.resize(self.decompressor.next_len(), 0);
+        let input_len = loop {
+            match self.reader.read(&mut self.input) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => break len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => return Err(err),
+            }
+        };
+
+        self.buf.clear();
+        self.pos = 0;
+        self.decompressor.update(&self.input[..input_len], &mut self.buf).map_err(invalid_data)?;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.buf.len() && !self.decompressor.is_end() {
+            self.read_chunk()?;
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read, Write};
+
+    #[test]
+    fn encoder() {
+        let text = "Hello עוֹלָם 👋".repeat(2 * 1024);
+
+        // Each character is split between multiple writes.
+        for write_len in [1, 2, 3, 1000, text.len()] {
+            let mut encoder = super::Encoder::new(Vec::new());
+            for bytes in text.as_bytes().chunks(write_len) {
+                encoder.write_all(bytes).unwrap();
+            }
+            let stream = encoder.finish().unwrap();
+
+            let (mut decompressor, mut decompressed_bytes) = (crate::stream::Decompressor::new(), Vec::new());
+            decompressor.update(&stream, &mut decompressed_bytes).unwrap();
+            assert_eq!(decompressor.finish(), Ok(()), "failed with write length {}", write_len);
+            assert!(decompressed_bytes == text.as_bytes(), "failed with write length {}", write_len);
+        }
+
+        // Incomplete character
+        let mut encoder = super::Encoder::new(Vec::new());
+        encoder.write_all(&"ö".as_bytes()[..1]).unwrap();
+        assert!(encoder.finish().is_err());
+
+        // Invalid character
+        let mut encoder = super::Encoder::new(Vec::new());
+        assert!(encoder.write_all(&[72, 0b10000000]).is_err());
+    }
+
+    #[test]
+    fn decoder() {
+        let text = "Hello עוֹלָם 👋\n".repeat(4 * 1024);
+        let mut encoder = super::Encoder::new(Vec::new());
+        for bytes in text.as_bytes().chunks(1000) {
+            encoder.write_all(bytes).unwrap();
+        }
+        let mut stream = encoder.finish().unwrap();
+        stream.extend_from_slice(b"Next");
+
+        // Read
+        let mut decoder = super::Decoder::new(stream.as_slice());
+        let mut decompressed_bytes = Vec::new();
+        let mut buf = [0; 7];
+        loop {
+            let len = decoder.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            decompressed_bytes.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(decompressed_bytes, text.as_bytes());
+        assert_eq!(decoder.into_inner(), b"Next");
+
+        // BufRead
+        let decoder = super::Decoder::new(stream.as_slice());
+        let lines = decoder.lines().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(lines.len(), 4 * 1024);
+        assert!(lines.iter().all(|line| line == "Hello עוֹלָם 👋"));
+
+        // Incomplete stream
+        let mut decoder = super::Decoder::new(&stream[..(stream.len() - 6)]);
+        let err = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // Invalid character
+        let mut decoder = super::Decoder::new([2, 0, 72, 215, 0, 0].as_slice());
+        let err = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -1,16 +1,39 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+
 /* private modules */
+mod buffer;
+mod checksum;
+mod context;
+mod emoji;
+mod error;
+mod header;
+mod options;
 mod utf8;
+mod window;
 
 /* private macros */
 #[macro_use]
 mod macros;
 
 /* public modules */
+pub mod frame;
 pub mod helper;
+#[cfg(feature = "std")]
+pub mod io;
+pub mod stream;
+
+use buffer::{Buffer, CountBuffer, SliceBuffer};
+use context::Context;
+pub use error::{CompressError, DecompressError};
+pub use options::{LengthHeader, Options};
 
 /// Returns `true` if all characters were ASCII and were successfully processed, otherwise `false`.
 #[inline(always)]
-fn handle_ascii(value: &mut &[u8], result: &mut Vec<u8>) -> bool {
+fn handle_ascii<B: Buffer>(value: &mut &[u8], result: &mut B) -> bool {
     // Check if only one character is left or if the second is a non-ASCII character.
     if value.len() == 1 || helper::test_sign_bit(value[1]) {
         result.push(value[0]);
@@ -31,14 +54,10 @@ fn handle_ascii(value: &mut &[u8], result: &mut Vec<u8>) -> bool {
     true
 }
 
-#[derive(Debug, PartialEq)]
-pub enum CompressError {
-    InvalidLength,
-    InvalidOrMissingPrefix(Vec<u8>),
-}
-
 /// Returns the compressed bytes or `CompressError`.
 /// 
+/// The bytes must be well-formed UTF-8 (RFC 3629), so e.g. overlong encodings and UTF-16 surrogates are rejected.
+/// 
 /// __TIP:__ Use the [`shrink_to_fit`](https://doc.rust-lang.org/beta/alloc/vec/struct.Vec.html#method.shrink_to_fit) function on the compressed bytes.
 /// 
 /// # Example
@@ -55,33 +74,249 @@ pub fn compress<T>(bytes: T) -> Result<Vec<u8>, CompressError>
 where 
     T: AsRef<[u8]>, 
 {
-    let mut value = bytes.as_ref();
+    let mut result = Vec::new();
+    compress_into(bytes, &mut result)?;
+    Ok(result)
+}
+
+/// Returns the compressed text or `CompressError`.
+/// 
+/// Unlike [`compress`], the characters are not checked to be well-formed, because a `&str` is always valid UTF-8.
+/// 
+/// # Example
+/// ```
+/// let result = utf_c::compress_str("ÄÖÜ").unwrap();
+/// assert_eq!(result, utf_c::compress("ÄÖÜ").unwrap());
+/// ```
+pub fn compress_str(text: &str) -> Result<Vec<u8>, CompressError> {
+    let value = text.as_bytes();
+    let mut result = Vec::with_capacity(max_compressed_len(value.len()));
+    compress_to::<_, false>(value, &Options::new(), &mut result)?;
+    Ok(result)
+}
+
+/// Returns the compressed bytes and the number of replacements or `CompressError`.
+/// 
+/// Each invalid sequence is replaced with U+FFFD (REPLACEMENT CHARACTER),
+/// following the same rules as [`String::from_utf8_lossy`].
+/// 
+/// # Example
+/// ```
+/// const BYTES: [u8; 4] = [72, 240, 144, 128];
+/// //                      |   |-Incomplete character
+/// //                      |-H
+/// let (result, replacements) = utf_c::compress_lossy(BYTES).unwrap();
+/// assert_eq!(result, utf_c::compress("H\u{FFFD}").unwrap());
+/// assert_eq!(replacements, 1);
+/// ```
+pub fn compress_lossy<T>(bytes: T) -> Result<(Vec<u8>, usize), CompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    const REPLACEMENT_CHARACTER: &[u8] = "\u{FFFD}".as_bytes();
+
+    let value = bytes.as_ref();
+    if value.is_empty() {
+        return Err(CompressError::InvalidLength);
+    }
+
+    // The length header needs the length after the replacements.
+    let data_len = value.utf8_chunks().fold(0, |data_len, chunk| {
+        let replacement_len = if chunk.invalid().is_empty() { 0 } else { REPLACEMENT_CHARACTER.len() };
+        data_len + chunk.valid().len() + replacement_len
+    });
+
+    let mut result = Vec::<u8>::with_capacity(max_compressed_len(data_len));
+    header::write(LengthHeader::Unary, data_len, &mut result);
+
+    let (options, mut context, mut replacements) = (Options::new(), Context::default(), 0);
+    for chunk in value.utf8_chunks() {
+        // All characters are valid, so we don't need to check them again.
+        compress_body::<_, false>(chunk.valid().as_bytes(), &options, &mut context, &mut result)?;
+        if !chunk.invalid().is_empty() {
+            compress_body::<_, false>(REPLACEMENT_CHARACTER, &options, &mut context, &mut result)?;
+            replacements += 1;
+        }
+    }
+
+    Ok((result, replacements))
+}
+
+/// Appends the compressed bytes to `result` or returns `CompressError`.
+/// 
+/// If an error occurs, `result` is left unchanged.
+/// 
+/// # Example
+/// ```
+/// let mut result = Vec::new();
+/// utf_c::compress_into("ÄÖ", &mut result).unwrap();
+/// utf_c::compress_into("Ü", &mut result).unwrap();
+/// assert_eq!(result, [4, 195, 132, 150, 2, 195, 156]);
+/// ```
+pub fn compress_into<T>(bytes: T, result: &mut Vec<u8>) -> Result<(), CompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let value = bytes.as_ref();
+    // We should use at least the uncompressed length to avoid multiple reallocations.
+    // For this reason, we recommend calling the function `shrink_to_fit` on the compressed bytes.
+    result.reserve(max_compressed_len(value.len()));
+
+    let result_len = result.len();
+    let compressed_result = compress_to::<_, true>(value, &Options::new(), result);
+    if compressed_result.is_err() {
+        result.truncate(result_len);
+    }
+    compressed_result
+}
+
+/// Writes the compressed bytes to `result` and returns their length or `CompressError`.
+/// 
+/// If `result` is too small, `CompressError::BufferTooSmall` contains the required length.
+/// 
+/// # Example
+/// ```
+/// let mut result = [0; 8];
+/// let len = utf_c::compress_into_slice("ÄÖÜ", &mut result).unwrap();
+/// assert_eq!(result[..len], [6, 195, 132, 150, 156]);
+/// 
+/// let mut result = [0; 4];
+/// let err = utf_c::compress_into_slice("ÄÖÜ", &mut result).unwrap_err();
+/// assert_eq!(err, utf_c::CompressError::BufferTooSmall { required: 5 });
+/// ```
+pub fn compress_into_slice<T>(bytes: T, result: &mut [u8]) -> Result<usize, CompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let mut buffer = SliceBuffer::from(result);
+    compress_to::<_, true>(bytes.as_ref(), &Options::new(), &mut buffer)?;
+    if !buffer.fits() {
+        return Err(CompressError::BufferTooSmall { required: buffer.len() });
+    }
+    Ok(buffer.len())
+}
+
+/// Returns the exact length of the bytes that [`compress`] would return or `CompressError`.
+/// 
+/// No memory is allocated for the compressed bytes.
+/// 
+/// # Example
+/// ```
+/// let len = utf_c::compressed_len("ÄÖÜ").unwrap();
+/// assert_eq!(len, utf_c::compress("ÄÖÜ").unwrap().len());
+/// ```
+pub fn compressed_len<T>(bytes: T) -> Result<usize, CompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let mut buffer = CountBuffer::default();
+    compress_to::<_, true>(bytes.as_ref(), &Options::new(), &mut buffer)?;
+    Ok(buffer.len())
+}
+
+/// Returns the maximum length of the bytes that [`compress`] can return for an input of `input_len` bytes.
+/// 
+/// This includes the length header and is never smaller than [`compressed_len`].
+/// 
+/// # Example
+/// ```
+/// const MAX_LEN: usize = utf_c::max_compressed_len(512);
+/// assert_eq!(MAX_LEN, 2 + 1 + 512);
+/// //                  |   |   |-Characters
+/// //                  |   |-Length (remainder)
+/// //                  |-Length (255 per byte)
+/// ```
+pub const fn max_compressed_len(input_len: usize) -> usize {
+    // A character is never longer after compression, because a prefix is only written
+    // instead of the same bytes of the original character.
+    (input_len / 255) + 1 + input_len
+}
+
+/// Returns the compressed bytes with the given `options` or `CompressError`.
+/// 
+/// The bytes can be decompressed with [`decompress_with`] and the same length header, checksum and prefix cache.
+/// With a container header (see [`Options::container`]), [`decompress_auto`] needs no `options`,
+/// and without any of these options, [`decompress`] is enough.
+/// 
+/// # Example
+/// ```
+/// use utf_c::{LengthHeader, Options};
+/// 
+/// const BYTES: [u8; 6] = [128, 1, 206, 177, 177, 177];
+/// //                      |       |    |-α  |-α  |-α ...
+/// //                      |       |-Prefix
+/// //                      |-Length (LEB128)
+/// let options = Options::new().length_header(LengthHeader::Varint);
+/// let result = utf_c::compress_with("α".repeat(64), &options).unwrap();
+/// assert_eq!(result[..6], BYTES);
+/// ```
+pub fn compress_with<T>(bytes: T, options: &Options) -> Result<Vec<u8>, CompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let value = bytes.as_ref();
+    let header_len = header::CONTAINER_LEN + header::len(options.get_length_header(), value.len());
+    let mut result = Vec::with_capacity(header_len + value.len() + checksum::CHECKSUM_LEN);
+    compress_to::<_, true>(value, options, &mut result)?;
+    Ok(result)
+}
+
+/// Returns the compressed bytes without any header or `CompressError`.
+/// 
+/// This is useful if the data is stored in an external frame (e.g. a database column),
+/// that already knows the compressed length. Unlike [`compress`], an empty input is allowed.
+/// 
+/// # Example
+/// ```
+/// const BYTES: [u8; 4] = [195, 132, 150, 156];
+/// //                      |    |-Ä  |-Ö  |-Ü
+/// //                      |-Prefix
+/// let result = utf_c::compress_raw("ÄÖÜ").unwrap();
+/// assert_eq!(result, BYTES);
+/// ```
+pub fn compress_raw<T>(bytes: T) -> Result<Vec<u8>, CompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let value = bytes.as_ref();
+    let mut result = Vec::with_capacity(value.len());
+    compress_body::<_, true>(value, &Options::new(), &mut Context::default(), &mut result)?;
+    Ok(result)
+}
+
+/// Writes the headers and the compressed bytes.
+fn compress_to<B: Buffer, const STRICT: bool>(value: &[u8], options: &Options, result: &mut B) -> Result<(), CompressError> {
     let value_len = value.len();
     if value_len == 0 {
         return Err(CompressError::InvalidLength);
     }
 
-    let data_len_count = value_len / 255;
-    let data_len_remainder = value_len % 255;
-
-    let mut result = Vec::<u8>::with_capacity(
-        data_len_count +
-        1              + // The value of `data_len_remainder`.
-        // We should use at least the uncompressed length to avoid multiple reallocations.
-        // For this reason, we recommend calling the function `shrink_to_fit` on the compressed bytes.
-        value_len
-    );
-    if data_len_count > 0 {
-        // We can use the unsafe functions, because we are using a larger
-        // capacity and this is our first data for this vector.
-        unsafe {
-            result.set_len(data_len_count);
-            result.as_mut_ptr().write_bytes(255, data_len_count);
-        }
+    if options.get_container() {
+        header::write_container(options, result);
+    }
+    header::write(options.get_length_header(), value_len, result);
+    compress_body::<_, STRICT>(value, options, &mut Context::new(options), result)?;
+    if options.get_checksum() {
+        result.extend_from_slice(&checksum::crc32c(value).to_le_bytes());
     }
-    result.push(data_len_remainder as u8);
+    Ok(())
+}
 
-    let mut last_utf8_prefix: &[u8] = &[];
+/// The core of all compress functions (without the length header).
+/// 
+/// The `context` contains the last written prefix and is updated, so that the next input can continue with it.
+/// If `STRICT` is `true`, each prefix is checked to be well-formed UTF-8 (RFC 3629).
+fn compress_body<B: Buffer, const STRICT: bool>(mut value: &[u8], options: &Options, context: &mut Context, result: &mut B) -> Result<(), CompressError> {
+    let value_len = value.len();
+    let windows = options.get_wide_windows() || options.get_narrow_windows();
+    let emoji = options.get_emoji();
+
+    // Continue with the window of the last input.
+    if context.window.is_some() {
+        value = &value[window::compress::<_, STRICT>(value, options, context, result)?..];
+    }
+    let initial_utf8_prefix = context.prefix;
+    let mut last_utf8_prefix: &[u8] = initial_utf8_prefix.as_slice();
 
     'heart: while !value.is_empty() {
         let utf8_value = utf8::Value::from(value);
@@ -90,20 +325,60 @@ where
             utf8::Unicode::Unknown => {
                 // We found a non-ASCII character with an invalid or missing prefix.
                 let err_result = value.iter().take(utf8::MAX_UTF8_BYTES).copied().collect::<Vec<u8>>();
-                return Err(CompressError::InvalidOrMissingPrefix(err_result));
+                return Err(CompressError::InvalidOrMissingPrefix { at: value_len - value.len(), bytes: err_result });
             },
             utf8::Unicode::Range00000_0007F => {
-                if handle_ascii(&mut value, &mut result) {
+                if handle_ascii(&mut value, result) {
                     // No characters left.
                     break 'heart;
                 }
                 continue 'heart;
             },
             _ => {
+                if emoji {
+                    let read = emoji::compress(value, last_utf8_prefix, result);
+                    if read > 0 {
+                        value = &value[read..];
+                        continue 'heart;
+                    }
+                }
+
+                if windows && let Some(window) = window::select(options, utf8_value.code_point()) {
+                    // The window is used until a character is not in a window.
+                    let at = value_len - value.len();
+                    window::enter(window, context, result);
+                    let read = window::compress::<_, STRICT>(value, options, context, result).map_err(|err| err.with_offset(at))?;
+                    value = &value[read..];
+                    continue 'heart;
+                }
+
                 let utf8_prefix = utf8_value.prefix();
                 if last_utf8_prefix != utf8_prefix {
+                    if STRICT && let Err(err) = utf8::check_prefix(utf8_prefix) {
+                        return Err(CompressError::from_prefix_error(err, value_len - value.len()));
+                    }
+
+                    // A back-reference needs only one byte instead of the whole prefix (the cache is empty if it is disabled).
+                    match context.cache.find(utf8_prefix) {
+                        Some(slot) if utf8_prefix.len() == 1 => {
+                            let utf8_len = utf8_value.len();
+                            let rest = &value[utf8_len..];
+                            let next_idx = helper::find_pos_byte_idx(rest).unwrap_or(rest.len());
+                            if rest[next_idx..].starts_with(last_utf8_prefix) {
+                                // A single character with a cached 1-byte prefix keeps the current prefix,
+                                // if the next character needs it again, e.g. a niqqud (0xD6) between Hebrew letters (0xD7).
+                                result.extend_from_slice(&[context::CACHE_SLOT + slot as u8, utf8_value.char()]);
+                                value = unsafe { value.get_unchecked(utf8_len..) };
+                                continue 'heart;
+                            }
+                            // The back-reference of a 1-byte prefix is always a single character, so the prefix is written.
+                            result.extend_from_slice(utf8_prefix);
+                        },
+                        Some(slot) => result.push(context::CACHE_SLOT + slot as u8),
+                        None => result.extend_from_slice(utf8_prefix),
+                    }
+                    context.cache.update(last_utf8_prefix);
                     last_utf8_prefix = utf8_prefix;
-                    result.extend_from_slice(utf8_prefix);
                 }
             }
         }
@@ -111,24 +386,25 @@ where
         let utf8_char = utf8_value.char();
         result.push(utf8_char);
         // We can use `unsafe` here because we know the length.
-        value = unsafe {
-            let utf8_len = utf8_value.len();
-            value.get_unchecked(utf8_len..)
-        };
+        let utf8_len = utf8_value.len();
+        value = unsafe { value.get_unchecked(utf8_len..) };
+
+        if utf8_len == 2 && !windows {
+            // The following characters with the same prefix only need their second byte,
+            // and the prefix has already been checked.
+            let read = helper::compress_prefix_run(value, last_utf8_prefix[0], result);
+            value = unsafe { value.get_unchecked(read..) };
+        }
     }
     
-    Ok(result)
-}
-
-#[derive(Debug, PartialEq)]
-pub enum DecompressError {
-    InvalidLength,
-    MissingBytes,
-    MissingPrefix(Vec<u8>),
+    context.prefix = utf8::Prefix::from(last_utf8_prefix);
+    Ok(())
 }
 
 /// Returns the decompressed bytes or `DecompressError`.
 /// 
+/// Each character is validated while decoding, so the decompressed bytes are always valid UTF-8.
+/// 
 /// # Example
 /// ```
 /// const BYTES: [u8; 5] = [6, 195, 132, 150, 156];
@@ -142,34 +418,378 @@ pub fn decompress<T>(bytes: T) -> Result<Vec<u8>, DecompressError>
 where 
     T: AsRef<[u8]>, 
 {
-    let mut value = bytes.as_ref();
-    let value_len = value.len();
-    if value_len < 2 {
-        return Err(DecompressError::InvalidLength);
+    let mut result = Vec::new();
+    decompress_into(bytes, &mut result)?;
+    Ok(result)
+}
+
+/// Returns the decompressed bytes or `DecompressError`, with or without a container header.
+/// 
+/// If the bytes start with a container header (see [`Options::container`]), the options are read from it.
+/// Otherwise the bytes are decompressed as UTF-C 1.0, like [`decompress`].
+/// 
+/// # Example
+/// ```
+/// use utf_c::{LengthHeader, Options};
+/// 
+/// let options = Options::new().container(true).length_header(LengthHeader::Varint);
+/// let compressed_bytes = utf_c::compress_with("ÄÖÜ", &options).unwrap();
+/// assert_eq!(compressed_bytes, [0, 85, 67, 1, 1, 6, 195, 132, 150, 156]);
+/// //                            |          |  |  |  |-Characters
+/// //                            |          |  |  |-Length
+/// //                            |          |  |-Flags
+/// //                            |          |-Version
+/// //                            |-Magic
+/// assert_eq!(utf_c::decompress_auto(compressed_bytes).unwrap(), "ÄÖÜ".as_bytes());
+/// 
+/// let compressed_bytes = utf_c::compress("ÄÖÜ").unwrap();
+/// assert_eq!(utf_c::decompress_auto(compressed_bytes).unwrap(), "ÄÖÜ".as_bytes());
+/// ```
+pub fn decompress_auto<T>(bytes: T) -> Result<Vec<u8>, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let bytes = bytes.as_ref();
+    let options = Options::new().container(header::has_container(bytes));
+    decompress_with(bytes, &options)
+}
+
+/// Returns the decompressed bytes of [`compress_raw`] or `DecompressError`.
+/// 
+/// All bytes of the input are decompressed. If the decompressed length is known (e.g. from metadata),
+/// it can be passed as `capacity` to allocate the memory only once. It is limited to what the input can decompress to.
+/// 
+/// # Example
+/// ```
+/// let result = utf_c::decompress_raw([195, 132, 150, 156], Some(6)).unwrap();
+/// assert_eq!(result, "ÄÖÜ".as_bytes());
+/// ```
+pub fn decompress_raw<T>(bytes: T, capacity: Option<usize>) -> Result<Vec<u8>, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let value = bytes.as_ref();
+    // A character is never shorter after decompression, but the given capacity is not trusted.
+    let mut result = Vec::with_capacity(capacity.map_or(value.len(), |capacity| self::capacity(value, capacity)));
+    decompress_body::<_, true>(value, &mut Context::default(), &mut result, usize::MAX)?;
+    Ok(result)
+}
+
+/// Appends the decompressed bytes to `result` or returns `DecompressError`.
+/// 
+/// If an error occurs, `result` is left unchanged.
+/// If the bytes continue after the length in the header, `DecompressError::TrailingData` is returned (see [`decompress_prefix_of`]).
+/// 
+/// # Example
+/// ```
+/// let mut result = b"Text: ".to_vec();
+/// utf_c::decompress_into([6, 195, 132, 150, 156], &mut result).unwrap();
+/// assert_eq!(result, "Text: ÄÖÜ".as_bytes());
+/// ```
+pub fn decompress_into<T>(bytes: T, result: &mut Vec<u8>) -> Result<(), DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let bytes = bytes.as_ref();
+    let (data_len, value) = header::read(LengthHeader::Unary, bytes)?;
+    result.reserve(capacity(value, data_len));
+
+    let result_len = result.len();
+    let decompressed_result = decompress_exact::<_, true>(value, bytes.len() - value.len(), data_len, &Options::new(), result);
+    if decompressed_result.is_err() {
+        result.truncate(result_len);
     }
+    decompressed_result
+}
 
-    let mut data_len = 0;
-    for idx in 0..=value_len {
-        if value_len == idx {
-            return Err(DecompressError::MissingBytes);
-        }
+/// Writes the decompressed bytes to `result` and returns their length or `DecompressError`.
+/// 
+/// If `result` is too small, `DecompressError::BufferTooSmall` contains the required length.
+/// 
+/// # Example
+/// ```
+/// let mut result = [0; 8];
+/// let len = utf_c::decompress_into_slice([6, 195, 132, 150, 156], &mut result).unwrap();
+/// assert_eq!(result[..len], *"ÄÖÜ".as_bytes());
+/// ```
+pub fn decompress_into_slice<T>(bytes: T, result: &mut [u8]) -> Result<usize, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let bytes = bytes.as_ref();
+    let (data_len, value) = header::read(LengthHeader::Unary, bytes)?;
+    let mut buffer = SliceBuffer::from(result);
+    decompress_exact::<_, true>(value, bytes.len() - value.len(), data_len, &Options::new(), &mut buffer)?;
+    if !buffer.fits() {
+        return Err(DecompressError::BufferTooSmall { required: buffer.len() });
+    }
+    Ok(buffer.len())
+}
 
-        let byte = value[idx];
-        if byte < 255 {
-            if idx > 1 {
-                data_len = (idx - 1) * 255;
-            }
-            data_len += byte as usize;
-            value = &value[(idx + 1)..];
-            break;
+/// Returns the decompressed text or `DecompressError`.
+/// 
+/// Each character is validated while decoding, so the bytes don't have to be scanned again by [`String::from_utf8`].
+/// 
+/// # Example
+/// ```
+/// let result = utf_c::decompress_to_string([6, 195, 132, 150, 156]).unwrap();
+/// assert_eq!(result, "ÄÖÜ");
+/// 
+/// // The second character `0xD7` is not a continuation byte.
+/// let result = utf_c::decompress_to_string([5, 215, 153, 215, 72]);
+/// assert!(matches!(result, Err(utf_c::DecompressError::InvalidUtf8 { at: 3, .. })));
+/// ```
+pub fn decompress_to_string<T>(bytes: T) -> Result<String, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let result = decompress(bytes)?;
+    // We can use `unsafe` here because all characters have been validated.
+    Ok(unsafe { String::from_utf8_unchecked(result) })
+}
+
+/// Returns the decompressed text or `DecompressError`, without validating the characters.
+/// 
+/// # Safety
+/// The bytes must have been compressed from valid UTF-8 (e.g. a `&str`) and must not have been modified since.
+/// Otherwise the returned `String` may contain invalid UTF-8.
+/// 
+/// # Example
+/// ```
+/// let compressed_bytes = utf_c::compress("ÄÖÜ").unwrap();
+/// let result = unsafe { utf_c::decompress_to_string_unchecked(compressed_bytes) }.unwrap();
+/// assert_eq!(result, "ÄÖÜ");
+/// ```
+pub unsafe fn decompress_to_string_unchecked<T>(bytes: T) -> Result<String, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let bytes = bytes.as_ref();
+    let (data_len, value) = header::read(LengthHeader::Unary, bytes)?;
+    let mut result = Vec::with_capacity(capacity(value, data_len));
+    decompress_exact::<_, false>(value, bytes.len() - value.len(), data_len, &Options::new(), &mut result)?;
+    // The caller guarantees that the bytes are valid UTF-8.
+    Ok(unsafe { String::from_utf8_unchecked(result) })
+}
+
+/// Returns the length of the decompressed bytes or `DecompressError`.
+/// 
+/// Only the length header is read, so this can be used to allocate memory before decompressing.
+/// 
+/// # Example
+/// ```
+/// let len = utf_c::decompressed_len([6, 195, 132, 150, 156]).unwrap();
+/// assert_eq!(len, "ÄÖÜ".len());
+/// ```
+pub fn decompressed_len<T>(bytes: T) -> Result<usize, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    header::read(LengthHeader::Unary, bytes.as_ref()).map(|(data_len, _)| data_len)
+}
+
+/// Returns the decompressed bytes with the given `options` or `DecompressError`.
+/// 
+/// The length header, the checksum and the prefix cache of the `options` must be the same as for [`compress_with`].
+/// If [`Options::container`] is set, the container header must exist and the options are read from it.
+/// 
+/// # Example
+/// ```
+/// use utf_c::{LengthHeader, Options};
+/// 
+/// let options = Options::new().length_header(LengthHeader::Varint);
+/// let result = utf_c::decompress_with([6, 195, 132, 150, 156], &options).unwrap();
+/// assert_eq!(result, "ÄÖÜ".as_bytes());
+/// ```
+pub fn decompress_with<T>(bytes: T, options: &Options) -> Result<Vec<u8>, DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let bytes = bytes.as_ref();
+    let (options, value) = if options.get_container() {
+        // The flags of the container header replace the given options.
+        header::read_container(bytes)?
+    } else {
+        (options.clone(), bytes)
+    };
+
+    let (data_len, mut value) = header::read(options.get_length_header(), value)?;
+    let offset = bytes.len() - value.len();
+
+    let mut expected_checksum = None;
+    if options.get_checksum() {
+        let trailer_idx = value.len().checked_sub(checksum::CHECKSUM_LEN).ok_or(DecompressError::MissingBytes)?;
+        let (body, trailer) = value.split_at(trailer_idx);
+        // We can use `unwrap` here because the trailer has exactly 4 bytes.
+        expected_checksum = Some(u32::from_le_bytes(trailer.try_into().unwrap()));
+        value = body;
+    }
+
+    let mut result = Vec::with_capacity(capacity(value, data_len));
+    decompress_exact::<_, true>(value, offset, data_len, &options, &mut result)?;
+
+    if let Some(expected) = expected_checksum {
+        let actual = checksum::crc32c(&result);
+        if actual != expected {
+            return Err(DecompressError::ChecksumMismatch { expected, actual });
         }
     }
+    Ok(result)
+}
+
+/// Returns the decompressed bytes of the first record and the number of read bytes, or `DecompressError`.
+/// 
+/// Decompression stops as soon as the length of the header is reached, so the record can be followed by other data
+/// (e.g. the next record).
+/// 
+/// # Example
+/// ```
+/// let bytes = [6, 195, 132, 150, 156, 1, 2, 3];
+/// let (result, read) = utf_c::decompress_prefix_of(bytes).unwrap();
+/// assert_eq!(result, "ÄÖÜ".as_bytes());
+/// assert_eq!(&bytes[read..], [1, 2, 3]);
+/// ```
+pub fn decompress_prefix_of<T>(bytes: T) -> Result<(Vec<u8>, usize), DecompressError> 
+where 
+    T: AsRef<[u8]>, 
+{
+    let bytes = bytes.as_ref();
+    let (data_len, value) = header::read(LengthHeader::Unary, bytes)?;
+    let offset = bytes.len() - value.len();
+
+    let mut result = Vec::with_capacity(capacity(value, data_len));
+    let read = decompress_record::<_, true>(value, offset, data_len, &Options::new(), &mut result)?;
+    Ok((result, offset + read))
+}
+
+/// Returns the capacity to allocate for a record with `data_len` bytes.
+/// 
+/// The length header is not trusted, because each compressed byte decompresses to at most 4 bytes.
+#[inline]
+fn capacity(value: &[u8], data_len: usize) -> usize {
+    data_len.min(value.len().saturating_mul(4))
+}
+
+/// Decompresses a record with `data_len` bytes and returns the number of read bytes or `DecompressError`.
+/// 
+/// `offset` is the position of `value` in the input and is added to the offsets of the errors.
+/// If the decompressed length differs from `data_len`, `DecompressError::LengthMismatch` is returned.
+#[inline]
+fn decompress_record<B: Buffer, const VALIDATE: bool>(value: &[u8], offset: usize, data_len: usize, options: &Options, result: &mut B) -> Result<usize, DecompressError> {
+    let result_len = result.len();
+    let read = decompress_body::<_, VALIDATE>(value, &mut Context::new(options), result, result_len.saturating_add(data_len))
+        .map_err(|err| err.with_offset(offset))?;
+
+    let actual = result.len() - result_len;
+    if actual != data_len {
+        return Err(DecompressError::LengthMismatch { expected: data_len, actual });
+    }
+    Ok(read)
+}
 
-    let mut result = Vec::<u8>::with_capacity(data_len);
+/// Decompresses a record like [`decompress_record`], but returns `DecompressError::TrailingData` if `value` continues after it.
+#[inline]
+fn decompress_exact<B: Buffer, const VALIDATE: bool>(value: &[u8], offset: usize, data_len: usize, options: &Options, result: &mut B) -> Result<(), DecompressError> {
+    let read = decompress_record::<_, VALIDATE>(value, offset, data_len, options, result)?;
+    if read < value.len() {
+        return Err(DecompressError::TrailingData { at: offset + read });
+    }
+    Ok(())
+}
 
-    let mut last_utf8_prefix: &[u8] = &[];
+/// The core of all decompress functions (without the length header).
+/// 
+/// The `context` contains the last read prefix and window and is updated, so that the next input can continue with it.
+/// If `VALIDATE` is `true`, each character is checked to be valid UTF-8, so the result is always valid UTF-8.
+/// 
+/// Stops as soon as `result` contains `limit` bytes and returns the number of read bytes.
+/// A character that crosses the limit is still written completely, so the caller can detect the mismatch.
+fn decompress_body<B: Buffer, const VALIDATE: bool>(value: &[u8], context: &mut Context, result: &mut B, limit: usize) -> Result<usize, DecompressError> {
+    let mut offset = 0;
+    while offset < value.len() && result.len() < limit {
+        let read = match context.window {
+            Some(_) => window::decompress(&value[offset..], context, result, limit),
+            None => decompress_chars::<_, VALIDATE>(&value[offset..], context, result, limit),
+        };
+        offset += read.map_err(|err| err.with_offset(offset))?;
+    }
+    Ok(offset)
+}
+
+/// Decompresses the characters outside of a window and returns the number of read bytes or `DecompressError`.
+/// 
+/// Stops after the code that enters a window, or as soon as `result` contains `limit` bytes.
+fn decompress_chars<B: Buffer, const VALIDATE: bool>(mut value: &[u8], context: &mut Context, result: &mut B, limit: usize) -> Result<usize, DecompressError> {
+    let input = value;
+    let value_len = value.len();
+    let mut cached_utf8_prefix = context.prefix;
+    let mut last_utf8_prefix: &[u8] = cached_utf8_prefix.as_slice();
+
+    'heart: while !value.is_empty() && result.len() < limit {
+        if context::is_code(value[0]) {
+            let at = value_len - value.len();
+            match value[0] {
+                context::ZWJ | context::VS16 | context::EMOJI_MODIFIER | context::REGIONAL_INDICATORS => {
+                    let read = emoji::decompress(value, result).map_err(|err| err.with_offset(at))?;
+                    value = &value[read..];
+                    continue 'heart;
+                },
+                context::WIDE_WINDOW => {
+                    let Some(&selector) = value.get(1) else {
+                        return Err(DecompressError::Truncated { at });
+                    };
+                    context.enter(context::Window::Wide(selector));
+                    value = &value[2..];
+                    break 'heart;
+                },
+                context::NARROW_WINDOW => {
+                    let Some(&selector) = value.get(1) else {
+                        return Err(DecompressError::Truncated { at });
+                    };
+                    context.enter(context::Window::Narrow(selector));
+                    value = &value[2..];
+                    break 'heart;
+                },
+                context::LAST_WINDOW => {
+                    let Some(window) = context.last_window else {
+                        return Err(DecompressError::MissingWindow { at });
+                    };
+                    context.enter(window);
+                    value = &value[1..];
+                    break 'heart;
+                },
+                context::CACHE_SLOT.. => {
+                    // The prefix is replaced with the prefix of the cache slot.
+                    let slot = (value[0] - context::CACHE_SLOT) as usize;
+                    let Some(utf8_prefix) = context.cache.get(slot) else {
+                        return Err(DecompressError::MissingPrefix { at, bytes: value[..1].to_vec() });
+                    };
+
+                    if let [lead] = *utf8_prefix.as_slice() {
+                        // A single character with a 1-byte prefix, the current prefix is kept.
+                        let Some(&utf8_char) = value.get(1) else {
+                            return Err(DecompressError::Truncated { at });
+                        };
+                        if VALIDATE && !utf8::is_continuation(utf8_char) {
+                            return Err(DecompressError::InvalidUtf8 { at: at + 1, bytes: [lead, utf8_char].to_vec() });
+                        }
+
+                        result.extend_from_slice(&[lead, utf8_char]);
+                        value = &value[2..];
+                        continue 'heart;
+                    }
+
+                    context.cache.update(last_utf8_prefix);
+                    cached_utf8_prefix = utf8_prefix;
+                    last_utf8_prefix = cached_utf8_prefix.as_slice();
+                    value = &value[1..];
+                    continue 'heart;
+                },
+                // Reserved codes are handled like invalid characters.
+                _ => {},
+            }
+        }
 
-    'heart: while !value.is_empty() {
         let utf8_value = utf8::Value::from(value);
         let (utf8_len, utf8_char): (usize, u8);
         
@@ -178,28 +798,53 @@ where
                 // We have found a utf8::Unicode::Unknown,
                 // which means we have a character with the same last prefix.
 
+                if utf8::len_from_first_byte(value[0]) > value.len() && value[1..].iter().all(|&byte| utf8::is_continuation(byte)) {
+                    // The input ends after a prefix (or a part of it), e.g. &[ 72, 215 ]
+                    //                                                              ^ Prefix without a character.
+                    return Err(DecompressError::Truncated { at: value_len - value.len() });
+                }
+
                 if last_utf8_prefix.is_empty() {
                     // Should only happen if there was no set for the first non-ASCII character,
                     // as in this example: &[ 72, 101, 108, 108, 111, 32, 149 ]
                     //                                                    ^ Non-ASCII character without a prefix.
                     let err_result = value.iter().take(utf8::MAX_UTF8_BYTES).copied().collect::<Vec<u8>>();
-                    return Err(DecompressError::MissingPrefix(err_result));
+                    return Err(DecompressError::MissingPrefix { at: value_len - value.len(), bytes: err_result });
                 }
 
                 utf8_len = 1;
                 utf8_char = value[0];
+
+                if VALIDATE && !utf8::is_continuation(utf8_char) {
+                    // The character is not a continuation byte, e.g. a prefix without a character.
+                    let err_result = [last_utf8_prefix, &[utf8_char]].concat();
+                    return Err(DecompressError::InvalidUtf8 { at: value_len - value.len(), bytes: err_result });
+                }
             },
             utf8::Unicode::Range00000_0007F => {
-                if handle_ascii(&mut value, &mut result) {
+                if handle_ascii(&mut value, result) {
                     // No characters left.
-                    break 'heart;
+                    value = &[];
+                }
+                if result.len() > limit {
+                    // The ASCII characters after the limit are read again by the caller (e.g. as the next record).
+                    let excess = result.len() - limit;
+                    result.truncate(limit);
+                    value = &input[(value_len - value.len() - excess)..];
                 }
                 continue 'heart;
             },
             _ => {
                 utf8_len = utf8_value.len();
                 utf8_char = utf8_value.char();
+                context.cache.update(last_utf8_prefix);
                 last_utf8_prefix = utf8_value.prefix();
+
+                if VALIDATE && !utf8::is_valid_prefix(last_utf8_prefix) {
+                    // The bit patterns are correct, but the character is e.g. an overlong encoding.
+                    let err_result = value[..utf8_len].to_vec();
+                    return Err(DecompressError::InvalidUtf8 { at: value_len - value.len(), bytes: err_result });
+                }
             }
         }
 
@@ -207,9 +852,18 @@ where
         result.push(utf8_char);
         // We can use `unsafe` here because we know the length.
         value = unsafe { value.get_unchecked(utf8_len..) };
+
+        if let [lead] = *last_utf8_prefix && result.len() < limit {
+            // The following characters with the same 1-byte prefix are written together,
+            // but not more than needed to reach the limit.
+            let run_len = value.len().min((limit - result.len()).div_ceil(2));
+            let read = helper::decompress_prefix_run(&value[..run_len], lead, result);
+            value = unsafe { value.get_unchecked(read..) };
+        }
     }
     
-    Ok(result)
+    context.prefix = utf8::Prefix::from(last_utf8_prefix);
+    Ok(value_len - value.len())
 }
 
 #[cfg(test)]
@@ -248,6 +902,388 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compress_and_decompress_into() {
+        let text = "Hello עוֹלָם";
+
+        // Vec (append)
+        let mut compressed_bytes = vec![42];
+        super::compress_into(text, &mut compressed_bytes).unwrap();
+        assert_eq!(compressed_bytes[0], 42);
+        assert_eq!(compressed_bytes[1..], super::compress(text).unwrap());
+
+        let mut decompressed_bytes = vec![42];
+        super::decompress_into(&compressed_bytes[1..], &mut decompressed_bytes).unwrap();
+        assert_eq!(decompressed_bytes[0], 42);
+        assert_eq!(&decompressed_bytes[1..], text.as_bytes());
+
+        // Vec (unchanged on error)
+        let mut unchanged_bytes = vec![42];
+        assert!(super::compress_into([72, 0b10000000], &mut unchanged_bytes).is_err());
+        assert!(super::decompress_into([2, 72, 149], &mut unchanged_bytes).is_err());
+        assert_eq!(unchanged_bytes, [42]);
+
+        // Slice
+        let compressed_len = compressed_bytes.len() - 1;
+        let mut slice = [0; 32];
+        assert_eq!(super::compress_into_slice(text, &mut slice), Ok(compressed_len));
+        assert_eq!(slice[..compressed_len], compressed_bytes[1..]);
+        assert_eq!(super::compress_into_slice(text, &mut slice[..compressed_len - 1]), Err(super::CompressError::BufferTooSmall { required: compressed_len }));
+
+        let mut slice = [0; 32];
+        assert_eq!(super::decompress_into_slice(&compressed_bytes[1..], &mut slice), Ok(text.len()));
+        assert_eq!(slice[..text.len()], *text.as_bytes());
+        assert_eq!(super::decompress_into_slice(&compressed_bytes[1..], &mut slice[..3]), Err(super::DecompressError::BufferTooSmall { required: text.len() }));
+    }
+
+    #[test]
+    fn compressed_and_decompressed_len() {
+        let test_cases: [String; 5] = [
+            "H".to_owned(),
+            "Hello עוֹלָם".to_owned(),
+            "a".repeat(254),
+            "a".repeat(255),
+            "α".repeat(512),
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress(&text).unwrap();
+            assert_eq!(super::compressed_len(&text), Ok(compressed_bytes.len()), "Compressed length does not match for: {}", text);
+            assert!(super::max_compressed_len(text.len()) >= compressed_bytes.len(), "Maximum compressed length is too small for: {}", text);
+            assert_eq!(super::decompressed_len(&compressed_bytes), Ok(text.len()), "Decompressed length does not match for: {}", text);
+        }
+
+        assert_eq!(super::decompressed_len([255, 2]), Ok(257));
+        assert_eq!(super::decompressed_len([255, 255, 2]), Ok(512));
+        assert_eq!(super::compressed_len([]), Err(super::CompressError::InvalidLength));
+    }
+
+    #[test]
+    fn decompress_to_string() {
+        let text = "Hello עוֹלָם";
+        let compressed_bytes = super::compress(text).unwrap();
+        assert_eq!(super::decompress_to_string(&compressed_bytes).as_deref(), Ok(text));
+        assert_eq!(unsafe { super::decompress_to_string_unchecked(&compressed_bytes) }.as_deref(), Ok(text));
+
+        let test_cases: [(&[u8], usize, &[u8]); 5] = [
+            (&[5, 215, 153, 215, 72],  3, &[215, 215]),           // Prefix without a character
+            (&[3, 215, 153, 72],       0, &[]),                   // Valid
+            (&[3, 224, 128, 128],      1, &[224, 128, 128]),      // Overlong encoding
+            (&[3, 237, 160, 128],      1, &[237, 160, 128]),      // UTF-16 surrogate
+            (&[4, 244, 144, 128, 128], 1, &[244, 144, 128, 128]), // Above U+10FFFF
+        ];
+
+        for (bytes, at, invalid_bytes) in test_cases {
+            let result = super::decompress_to_string(bytes);
+            if invalid_bytes.is_empty() {
+                assert!(result.is_ok(), "Decompression failed for {:?}", bytes);
+            } else {
+                let err = super::DecompressError::InvalidUtf8 { at, bytes: invalid_bytes.to_vec() };
+                assert_eq!(result, Err(err.clone()), "Decompression should have failed for {:?}", bytes);
+                // All decompress functions validate the characters.
+                assert_eq!(super::decompress(bytes), Err(err));
+                let raw_err = super::DecompressError::InvalidUtf8 { at: at - 1, bytes: invalid_bytes.to_vec() };
+                assert_eq!(super::decompress_raw(&bytes[1..], None), Err(raw_err));
+            }
+        }
+
+        // Without validation (`decompress_to_string_unchecked`), the last prefix is reused for any byte.
+        let mut result = Vec::new();
+        let read = super::decompress_body::<_, false>(&[215, 153, 215, 72], &mut super::Context::default(), &mut result, usize::MAX);
+        assert_eq!(read, Ok(4));
+        assert_eq!(result, [215, 153, 215, 215, 72]);
+    }
+
+    #[test]
+    fn compress_and_decompress_with() {
+        let varint = super::Options::new().length_header(super::LengthHeader::Varint);
+        let test_cases: [String; 4] = [
+            "Hello עוֹלָם".to_owned(),
+            "a".repeat(127),
+            "a".repeat(128),
+            "α".repeat(1024 * 1024),
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress_with(&text, &varint).unwrap();
+            let unary_bytes = super::compress(&text).unwrap();
+            // Only the length header is different.
+            let header_len = super::header::len(super::LengthHeader::Varint, text.len());
+            assert!(compressed_bytes[header_len..] == unary_bytes[super::header::len(super::LengthHeader::Unary, text.len())..], "Compressed bytes does not match for: {}", text);
+            assert!(super::decompress_with(&compressed_bytes, &varint).unwrap() == text.as_bytes(), "Decompressed bytes does not match for: {}", text);
+        }
+
+        // The default options are compatible with UTF-C 1.0.
+        let compressed_bytes = super::compress("Hello עוֹלָם").unwrap();
+        assert_eq!(super::compress_with("Hello עוֹלָם", &super::Options::default()).unwrap(), compressed_bytes);
+        assert_eq!(super::decompress_with(&compressed_bytes, &super::Options::default()).unwrap(), "Hello עוֹלָם".as_bytes());
+
+        // The length header is larger than the input can decompress to (no allocation of that size).
+        assert_eq!(super::decompress_with([255, 255, 255, 255, 255, 255, 255, 255, 255, 1, 72], &varint), Err(super::DecompressError::LengthMismatch { expected: usize::MAX, actual: 1 }));
+        assert_eq!(super::decompress_with([128, 128, 128, 128, 64, 72], &varint), Err(super::DecompressError::LengthMismatch { expected: 1 << 34, actual: 1 }));
+    }
+
+    #[test]
+    fn decompress_auto() {
+        let text = "Hello עוֹלָם";
+        let test_cases: [super::Options; 6] = [
+            super::Options::new(),
+            super::Options::new().container(true),
+            super::Options::new().container(true).checksum(true),
+            super::Options::new().container(true).length_header(super::LengthHeader::Varint),
+            super::Options::new().container(true).prefix_cache(true).wide_windows(true).narrow_windows(true).emoji(true),
+            super::Options::new().length_header(super::LengthHeader::Varint), // Not detectable
+        ];
+
+        for options in test_cases {
+            let compressed_bytes = super::compress_with(text, &options).unwrap();
+            assert_eq!(super::decompress_with(&compressed_bytes, &options).unwrap(), text.as_bytes(), "Decompression failed for {:?}", options);
+            if options.get_container() || options.get_length_header() == super::LengthHeader::Unary {
+                assert_eq!(super::decompress_auto(&compressed_bytes).unwrap(), text.as_bytes(), "Detection failed for {:?}", options);
+            }
+            if options.get_container() {
+                assert_eq!(super::Options::detect(&compressed_bytes), Ok(options.clone()), "Detected options does not match for {:?}", options);
+            }
+        }
+
+        // The container header is required.
+        let compressed_bytes = super::compress(text).unwrap();
+        assert_eq!(super::decompress_with(&compressed_bytes, &super::Options::new().container(true)), Err(super::DecompressError::MissingContainer));
+
+        // The offsets include the container header.
+        assert_eq!(super::decompress_auto([0, 85, 67, 1, 0, 1, 149]), Err(super::DecompressError::MissingPrefix { at: 6, bytes: vec![149] }));
+
+        // The varint length of the container is larger than the input can decompress to.
+        assert_eq!(super::decompress_auto([0, 85, 67, 1, 1, 255, 255, 255, 255, 255, 255, 255, 255, 255, 1, 72]), Err(super::DecompressError::LengthMismatch { expected: usize::MAX, actual: 1 }));
+        assert_eq!(super::decompress_auto([0, 85, 67, 1, 1, 128, 128, 128, 128, 64, 72]), Err(super::DecompressError::LengthMismatch { expected: 1 << 34, actual: 1 }));
+    }
+
+    #[test]
+    fn checksum() {
+        let text = "Hello עוֹלָם";
+        let options = super::Options::new().checksum(true);
+        let compressed_bytes = super::compress_with(text, &options).unwrap();
+        assert_eq!(compressed_bytes[..(compressed_bytes.len() - 4)], super::compress(text).unwrap());
+        assert_eq!(super::decompress_with(&compressed_bytes, &options).unwrap(), text.as_bytes());
+
+        // Each modified character is detected (the length header is skipped).
+        for idx in 1..(compressed_bytes.len() - 4) {
+            let mut modified_bytes = compressed_bytes.clone();
+            modified_bytes[idx] ^= 0b00000001;
+            assert!(super::decompress_with(&modified_bytes, &options).is_err(), "Corruption was not detected at {}", idx);
+        }
+
+        // Modified checksum
+        let mut modified_bytes = compressed_bytes.clone();
+        *modified_bytes.last_mut().unwrap() ^= 0b00000001;
+        assert!(matches!(super::decompress_with(&modified_bytes, &options), Err(super::DecompressError::ChecksumMismatch { .. })));
+
+        // Missing checksum
+        assert_eq!(super::decompress_with([2, 72, 72], &options), Err(super::DecompressError::MissingBytes));
+    }
+
+    #[test]
+    fn compress_and_decompress_raw() {
+        let test_cases: [&str; 4] = [
+            "",
+            "H",
+            "Hello עוֹלָם",
+            "👋 Hello world",
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress_raw(text).unwrap();
+            if !text.is_empty() {
+                // Only the length header is missing.
+                let unary_bytes = super::compress(text).unwrap();
+                assert!(unary_bytes.ends_with(&compressed_bytes) && unary_bytes.len() == compressed_bytes.len() + 1, "Compressed bytes does not match for: {}", text);
+            }
+
+            for capacity in [None, Some(0), Some(text.len())] {
+                let decompressed_bytes = super::decompress_raw(&compressed_bytes, capacity).unwrap();
+                assert!(decompressed_bytes == text.as_bytes(), "Decompressed bytes does not match for: {}", text);
+            }
+        }
+
+        assert!(super::compress_raw([72, 0b10000000]).is_err());
+        assert_eq!(super::decompress_raw([72, 149], None), Err(super::DecompressError::MissingPrefix { at: 1, bytes: vec![149] }));
+
+        // The capacity is limited by the input.
+        for capacity in [1 << 40, usize::MAX] {
+            let decompressed_bytes = super::decompress_raw([72], Some(capacity)).unwrap();
+            assert!(decompressed_bytes == b"H" && decompressed_bytes.capacity() < 1024, "Too much memory was reserved: {}", decompressed_bytes.capacity());
+        }
+    }
+
+    #[test]
+    fn prefix_cache() {
+        let options = super::Options::new().prefix_cache(true);
+        let test_cases: [&str; 7] = [
+            "Hello עוֹלָם",
+            "Zażółć gęślą jaźń",
+            "Pchnąć w tę łódź jeża lub ośm skrzyń fig. Mężny bądź, chroń pułk twój i sześć flag.",
+            "नमस्ते दुनिया, आप कैसे हैं?",
+            "Tiếng Việt có dấu thanh điệu",
+            "日本語のテキストと中文文本",
+            "👋 Hello world! 😀 Grüße, नमस्ते 🌍",
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress_with(text, &options).unwrap();
+            assert!(compressed_bytes.len() < super::compress(text).unwrap().len(), "Compressed bytes are not shorter for: {}", text);
+            assert!(super::decompress_with(&compressed_bytes, &options).unwrap() == text.as_bytes(), "Decompressed bytes does not match for: {}", text);
+        }
+
+        // The prefix of the niqqud (0xD6) is used for a single character between the Hebrew letters (0xD7).
+        assert_eq!(super::compress_with("Hello עוֹלָם", &options).unwrap(), [
+            18, 72, 101, 108, 108, 111, 32,
+            215, 162, 149, // ע ו
+            214, 185,      // ֹ
+            252, 156,      // ל (slot 0: 0xD7, without changing the prefix)
+            184,           // ָ
+            215, 157,      // ם
+        ]);
+
+        // The prefixes of Devanagari alternate (0xE0 0xA4 and 0xE0 0xA5).
+        let text = "नमस्ते";
+        assert_eq!(super::compress_with(text, &options).unwrap(), [
+            18,
+            224, 164, 168, 174, 184, // न म स
+            224, 165, 141,           // ्
+            252, 164,                // त (slot 0: 0xE0 0xA4)
+            253, 135,                // े (slot 1: 0xE0 0xA5)
+        ]);
+
+        // Cut off or invalid character after a 1-byte prefix
+        assert_eq!(super::decompress_with([6, 215, 153, 214, 185, 252], &options), Err(super::DecompressError::Truncated { at: 5 }));
+        assert_eq!(super::decompress_with([6, 215, 153, 214, 185, 252, 72], &options), Err(super::DecompressError::InvalidUtf8 { at: 6, bytes: vec![215, 72] }));
+
+        // Unused cache slot
+        assert_eq!(super::decompress_with([2, 255, 128], &options), Err(super::DecompressError::MissingPrefix { at: 1, bytes: vec![255] }));
+        // Without the option, the cache is not used by the decoder.
+        let compressed_bytes = super::compress_with(text, &options).unwrap();
+        assert_eq!(super::decompress(&compressed_bytes), Err(super::DecompressError::MissingPrefix { at: 9, bytes: vec![252] }));
+    }
+
+    #[test]
+    fn wide_windows() {
+        let options = super::Options::new().wide_windows(true);
+        let test_cases: [&str; 7] = [
+            "这是一个测试，它包含标点符号。",
+            "日本語のテキストです。「カタカナ」も含みます。",
+            "안녕하세요, 세계! 漢字도 있습니다.",
+            "Hello 世界 עוֹלָם 👋 世界",
+            "ESC \x1B inside 中文 \x1B\x1B",
+            "中文\u{FF0C}中文\u{FF0C}中文",
+            "한글！ＡＢＣ 中文 ｶﾀｶﾅ 걁",
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress_with(text, &options).unwrap();
+            assert!(super::decompress(&compressed_bytes).unwrap() == text.as_bytes(), "Decompressed bytes does not match for: {}", text);
+            assert_eq!(super::decompress_to_string(&compressed_bytes).as_deref(), Ok(text), "Decompressed text does not match for: {}", text);
+        }
+
+        // Chinese text needs 2/3 of its UTF-8 length (with the fullwidth punctuation).
+        let text = "这是一个测试，它包含标点符号。".repeat(16);
+        let compressed_bytes = super::compress_with(&text, &options).unwrap();
+        assert_eq!(compressed_bytes.len(), 3 + 2 + text.len() * 2 / 3);
+        //                                 |   |   |-Characters
+        //                                 |   |-Window
+        //                                 |-Length
+
+        // The fullwidth comma is at the end of each wide window, so the window is not left.
+        assert_eq!(super::compress_with("中，中", &options).unwrap(), [
+            9,
+            245, 46, 160, 45, // Window (0x2E00) and 中
+            255, 12,          // ， (offset 0x7F0C)
+            160, 45,          // 中
+        ]);
+
+        // Invalid codes
+        assert_eq!(super::decompress([1, 249]), Err(super::DecompressError::MissingWindow { at: 1 }));
+        assert_eq!(super::decompress([3, 245, 46, 159]), Err(super::DecompressError::Truncated { at: 3 }));
+        assert_eq!(super::decompress([3, 245, 128, 216, 0]), Err(super::DecompressError::InvalidUtf8 { at: 3, bytes: vec![216, 0] }));
+    }
+
+    #[test]
+    fn narrow_windows() {
+        let options = super::Options::new().narrow_windows(true);
+        let test_cases: [&str; 7] = [
+            "Hello עוֹלָם",
+            "नमस्ते दुनिया, आप कैसे हैं?",
+            "আমি বাংলায় গান গাই",
+            "வணக்கம் உலகம்",
+            "สวัสดีชาวโลก",
+            "Привет, мир! Ґанок і їжак.",
+            "Γειά σου Κόσμε, Grüße 👋 مرحبا بالعالم",
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress_with(text, &options).unwrap();
+            assert!(compressed_bytes.len() <= super::compress(text).unwrap().len(), "Compressed bytes are longer for: {}", text);
+            assert!(super::decompress(&compressed_bytes).unwrap() == text.as_bytes(), "Decompressed bytes does not match for: {}", text);
+            assert_eq!(super::decompress_to_string(&compressed_bytes).as_deref(), Ok(text), "Decompressed text does not match for: {}", text);
+
+            // Both windows can be used together.
+            let options = options.clone().wide_windows(true).prefix_cache(true);
+            let compressed_bytes = super::compress_with(text, &options).unwrap();
+            assert_eq!(super::decompress_to_string(&compressed_bytes).as_deref(), Ok(text), "Decompressed text does not match for: {}", text);
+        }
+
+        // A typical Hindi sentence needs only one window.
+        let text = "नमस्ते दुनिया";
+        assert_eq!(super::compress_with(text, &options).unwrap(), [
+            37,
+            246, 18, // Window (0x0900)
+            168, 174, 184, 205, 164, 199, 32, 166, 193, 168, 191, 175, 190,
+        ]);
+
+        let mixed_text = "日本語とहिन्दी 中文";
+        let options = options.wide_windows(true);
+        let compressed_bytes = super::compress_with(mixed_text, &options).unwrap();
+        assert_eq!(super::decompress_to_string(&compressed_bytes).as_deref(), Ok(mixed_text));
+    }
+
+    #[test]
+    fn emoji() {
+        let options = super::Options::new().emoji(true);
+        let test_cases: [&str; 6] = [
+            "👨‍👩‍👧",
+            "👋🏽 👍🏻👍🏿",
+            "🇩🇪🇫🇷🇺🇸",
+            "❤️ ⚽️ ☺️",
+            "🏳️‍🌈 🧑🏽‍💻 👩‍❤️‍👨",
+            "Hello 👋 “quoted” — עוֹלָם 🇩",
+        ];
+
+        for text in test_cases {
+            let compressed_bytes = super::compress_with(text, &options).unwrap();
+            assert!(compressed_bytes.len() <= super::compress(text).unwrap().len(), "Compressed bytes are longer for: {}", text);
+            assert!(super::decompress(&compressed_bytes).unwrap() == text.as_bytes(), "Decompressed bytes does not match for: {}", text);
+            assert_eq!(super::decompress_to_string(&compressed_bytes).as_deref(), Ok(text), "Decompressed text does not match for: {}", text);
+        }
+
+        // The prefix of the family is only written once.
+        assert_eq!(super::compress_with("👨‍👩‍👧", &options).unwrap(), [
+            18,
+            240, 159, 145, 168, // 👨
+            192, 169,           // ZWJ and 👩
+            192, 167,           // ZWJ and 👧
+        ]);
+        assert_eq!(super::compress_with("👋🏽🇩🇪", &options).unwrap(), [
+            16,
+            240, 159, 145, 139, // 👋
+            247, 189,           // 🏽
+            248, 169, 170,      // 🇩🇪
+        ]);
+
+        // Invalid codes
+        assert_eq!(super::decompress([4, 247]), Err(super::DecompressError::Truncated { at: 1 }));
+        assert_eq!(super::decompress([4, 247, 128]), Err(super::DecompressError::InvalidUtf8 { at: 1, bytes: vec![247, 128] }));
+        assert_eq!(super::decompress([8, 248, 166, 72]), Err(super::DecompressError::InvalidUtf8 { at: 1, bytes: vec![248, 166, 72] }));
+    }
+
     #[test]
     fn compress_invalid_input() {
         let test_cases: [&[u8]; 2] = [
@@ -261,6 +1297,111 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compress_ill_formed_input() {
+        let test_cases: [(&[u8], super::CompressError); 7] = [
+            (&[192, 128],               super::CompressError::OverlongEncoding { at: 0 }), // U+0000
+            (&[72, 224, 128, 128],      super::CompressError::OverlongEncoding { at: 1 }), // U+0000
+            (&[240, 128, 128, 128],     super::CompressError::OverlongEncoding { at: 0 }), // U+0000
+            (&[237, 160, 128],          super::CompressError::Surrogate { at: 0 }),        // U+D800
+            (&[72, 237, 191, 191],      super::CompressError::Surrogate { at: 1 }),        // U+DFFF
+            (&[244, 144, 128, 128],     super::CompressError::OutOfRange { at: 0 }),       // U+110000
+            (&[72, 245, 128, 128, 128], super::CompressError::OutOfRange { at: 1 }),       // U+140000
+        ];
+
+        for (invalid_data, err) in test_cases {
+            let result = super::compress(invalid_data);
+            assert_eq!(result, Err(err), "Compression should have failed for {:?}", invalid_data);
+        }
+
+        // The boundaries are valid.
+        let text = "\u{80}\u{7FF}\u{800}\u{D7FF}\u{E000}\u{FFFF}\u{10000}\u{10FFFF}";
+        assert_eq!(super::compress(text), super::compress_str(text));
+        assert!(super::compress(text).is_ok());
+    }
+
+    #[test]
+    fn compress_lossy() {
+        let test_cases: [(&[u8], usize); 7] = [
+            (b"Hello world",             0),
+            (b"Hello \xF0\x90\x80World", 1), // Incomplete character
+            (b"\x80Hello",               1), // Missing prefix
+            (b"\xC0\x80",                2), // Overlong encoding
+            (b"\xED\xA0\x80",            3), // UTF-16 surrogate
+            (b"\xF4\x90\x80\x80",        4), // Above U+10FFFF
+            ("שלום\u{FFFD}".as_bytes(),  0), // Valid replacement character
+        ];
+
+        for (bytes, replacements) in test_cases {
+            let text = String::from_utf8_lossy(bytes);
+            let result = super::compress_lossy(bytes);
+            assert_eq!(result, Ok((super::compress(text.as_bytes()).unwrap(), replacements)), "Compression failed for {:?}", bytes);
+        }
+
+        assert_eq!(super::compress_lossy([]), Err(super::CompressError::InvalidLength));
+    }
+
+    #[test]
+    fn truncated_and_trailing_data() {
+        use super::DecompressError;
+
+        let compressed_bytes = super::compress("Hello ÄÖÜ").unwrap();
+        let cut_off_bytes = &compressed_bytes[..(compressed_bytes.len() - 1)];
+        let trailing_bytes = [compressed_bytes.as_slice(), b"!"].concat();
+        assert_eq!(super::decompress(cut_off_bytes), Err(DecompressError::LengthMismatch { expected: 12, actual: 10 }));
+        assert_eq!(super::decompress(&trailing_bytes), Err(DecompressError::TrailingData { at: compressed_bytes.len() }));
+        assert_eq!(super::decompress_to_string(&trailing_bytes), Err(DecompressError::TrailingData { at: compressed_bytes.len() }));
+        assert_eq!(super::decompress_into_slice(cut_off_bytes, &mut [0; 16]), Err(DecompressError::LengthMismatch { expected: 12, actual: 10 }));
+
+        // The input ends after the prefix `E4 B8`.
+        let compressed_bytes = super::compress("Hello 中").unwrap();
+        let cut_off_bytes = &compressed_bytes[..(compressed_bytes.len() - 1)];
+        assert_eq!(super::decompress(cut_off_bytes), Err(DecompressError::Truncated { at: 7 }));
+        assert_eq!(super::decompress_prefix_of(cut_off_bytes), Err(DecompressError::Truncated { at: 7 }));
+
+        // The length continues in the second byte.
+        assert_eq!(super::decompressed_len([255, 2, 72]), Ok(257));
+        assert_eq!(super::decompress([255, 2, 72]), Err(DecompressError::LengthMismatch { expected: 257, actual: 1 }));
+    }
+
+    #[test]
+    fn decompress_prefix_of() {
+        let window_options = super::Options::new().wide_windows(true);
+        let records = [
+            super::compress("Hello ÄÖÜ").unwrap(),
+            super::compress("Hello").unwrap(),
+            super::compress_with("日本語 text", &window_options).unwrap(),
+            super::compress("👋").unwrap(),
+        ];
+
+        let mut bytes = records.concat();
+        for record in &records {
+            let (result, read) = super::decompress_prefix_of(&bytes).unwrap();
+            assert_eq!(result, super::decompress(record).unwrap());
+            assert_eq!(read, record.len());
+            bytes.drain(..read);
+        }
+        assert!(bytes.is_empty());
+
+        let cut_off_bytes = &records[0][..(records[0].len() - 1)];
+        assert_eq!(super::decompress_prefix_of(cut_off_bytes), Err(super::DecompressError::LengthMismatch { expected: 12, actual: 10 }));
+
+        // The length ends in the middle of a run of characters with the same prefix.
+        let mut bytes = super::compress("α".repeat(200)).unwrap();
+        bytes.splice(..2, [200]);
+        let (result, read) = super::decompress_prefix_of(&bytes).unwrap();
+        assert_eq!(result, "α".repeat(100).as_bytes());
+        assert_eq!(bytes[read..], [177].repeat(100));
+
+        // The unary length header is larger than the input can decompress to (no allocation of that size).
+        let bytes = [[255].repeat(4096), vec![0, 72]].concat();
+        let expected = Err(super::DecompressError::LengthMismatch { expected: 4096 * 255, actual: 1 });
+        assert_eq!(super::decompress_prefix_of(&bytes), expected);
+        let mut result = Vec::new();
+        assert_eq!(super::decompress_into(&bytes, &mut result), expected.map(|_| ()));
+        assert!(result.is_empty() && result.capacity() < 4096, "Too much memory was reserved: {}", result.capacity());
+    }
+
     #[test]
     fn decompress_invalid_input() {
         let test_cases: [&[u8]; 3] = [
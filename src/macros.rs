@@ -3,6 +3,7 @@ macro_rules! init_features {
         @MACRO: $macro:ident;
         $(@FEATURE: $feature_mode:ident: $feature:tt;)*
     ) => (
+        #[cfg(feature = "std")]
         macro_rules! feature_detected {
             $(
                 ($feature_mode) => {
@@ -13,6 +14,19 @@ macro_rules! init_features {
                 false
             };
         }
+
+        // Without `std`, the SIMD instructions can only be chosen at compile time.
+        #[cfg(not(feature = "std"))]
+        macro_rules! feature_detected {
+            $(
+                ($feature_mode) => {
+                    cfg!(target_feature = $feature)
+                };
+            )*
+            ($unknown_feature_mode:tt) => {
+                false
+            };
+        }
     );
 }
 
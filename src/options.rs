@@ -0,0 +1,243 @@
+use crate::{header, DecompressError};
+
+/// The encoding of the length header at the beginning of the compressed bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum LengthHeader {
+    /// `length / 255` bytes with the value `255`, followed by `length % 255` (UTF-C 1.0).
+    #[default]
+    Unary,
+    /// LEB128: 7 bits per byte, least significant first, at most 10 bytes.
+    ///
+    /// E.g. a length of 1 MiB needs 3 bytes instead of 4113 bytes.
+    Varint,
+}
+
+/// Options for [`compress_with`](crate::compress_with) and [`decompress_with`](crate::decompress_with).
+///
+/// The length header, the checksum and the prefix cache must be the same for compression and decompression,
+/// unless they are read from a container header (see [`Options::container`]).
+/// The other encoding modes (e.g. [`Options::wide_windows`]) only affect compression, because the decoder always understands their codes.
+/// So without these options, the compressed bytes can still be decompressed with [`decompress`](crate::decompress).
+///
+/// # Example
+/// ```
+/// use utf_c::{LengthHeader, Options};
+///
+/// let options = Options::new().length_header(LengthHeader::Varint);
+/// let compressed_bytes = utf_c::compress_with("a".repeat(300), &options).unwrap();
+/// assert_eq!(compressed_bytes[..2], [172, 2]);
+/// assert_eq!(utf_c::decompress_with(&compressed_bytes, &options).unwrap(), "a".repeat(300).as_bytes());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Options {
+    length_header: LengthHeader,
+    container: bool,
+    checksum: bool,
+    prefix_cache: bool,
+    wide_windows: bool,
+    narrow_windows: bool,
+    emoji: bool,
+}
+
+impl Options {
+    /// Creates the default options, which are compatible with UTF-C 1.0.
+    pub const fn new() -> Self {
+        Self {
+            length_header: LengthHeader::Unary,
+            container: false,
+            checksum: false,
+            prefix_cache: false,
+            wide_windows: false,
+            narrow_windows: false,
+            emoji: false,
+        }
+    }
+
+    /// Returns the options stored in the container header, or the default options (UTF-C 1.0) if there is none.
+    ///
+    /// # Example
+    /// ```
+    /// use utf_c::{LengthHeader, Options};
+    ///
+    /// let options = Options::new().container(true).length_header(LengthHeader::Varint);
+    /// let compressed_bytes = utf_c::compress_with("ÄÖÜ", &options).unwrap();
+    /// assert_eq!(Options::detect(&compressed_bytes), Ok(options));
+    ///
+    /// let compressed_bytes = utf_c::compress("ÄÖÜ").unwrap();
+    /// assert_eq!(Options::detect(&compressed_bytes), Ok(Options::new()));
+    /// ```
+    pub fn detect<T>(bytes: T) -> Result<Self, DecompressError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        if !header::has_container(bytes) {
+            return Ok(Self::new());
+        }
+        header::read_container(bytes).map(|(options, _)| options)
+    }
+
+    /// Sets whether a container header (magic bytes, format version and flags) is written before the length header.
+    ///
+    /// With a container header, the data is self-describing and can be decompressed with [`decompress_auto`](crate::decompress_auto).
+    pub const fn container(mut self, container: bool) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Returns whether a container header is written.
+    pub const fn get_container(&self) -> bool {
+        self.container
+    }
+
+    /// Sets the encoding of the length header.
+    pub const fn length_header(mut self, length_header: LengthHeader) -> Self {
+        self.length_header = length_header;
+        self
+    }
+
+    /// Returns the encoding of the length header.
+    pub const fn get_length_header(&self) -> LengthHeader {
+        self.length_header
+    }
+
+    /// Sets whether a CRC-32C of the uncompressed bytes is written after the compressed bytes.
+    ///
+    /// The checksum is verified during decompression, so that corrupted data is detected
+    /// (e.g. a modified character that would still decode into valid UTF-8).
+    ///
+    /// # Example
+    /// ```
+    /// use utf_c::{DecompressError, Options};
+    ///
+    /// let options = Options::new().checksum(true);
+    /// let mut compressed_bytes = utf_c::compress_with("ÄÖÜ", &options).unwrap();
+    /// assert_eq!(compressed_bytes.len(), 5 + 4);
+    ///
+    /// compressed_bytes[3] ^= 0b00000001;
+    /// let result = utf_c::decompress_with(&compressed_bytes, &options);
+    /// assert!(matches!(result, Err(DecompressError::ChecksumMismatch { .. })));
+    /// ```
+    pub const fn checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Returns whether a checksum is written.
+    pub const fn get_checksum(&self) -> bool {
+        self.checksum
+    }
+
+    /// Sets whether the last prefixes are remembered, so that they can be reused with a 1-byte back-reference.
+    ///
+    /// This helps if characters with different prefixes alternate (e.g. Devanagari, Vietnamese, Hebrew with niqqud or Polish).
+    /// A back-reference to a 1-byte prefix is only used for a single character, so that the current prefix is kept.
+    /// The decoder only keeps the cache up to date with this option, so it must also be set for [`decompress_with`](crate::decompress_with).
+    ///
+    /// # Example
+    /// ```
+    /// use utf_c::Options;
+    ///
+    /// let text = "नमस्ते दुनिया";
+    /// let options = Options::new().prefix_cache(true);
+    /// let compressed_bytes = utf_c::compress_with(text, &options).unwrap();
+    /// assert!(compressed_bytes.len() < utf_c::compress(text).unwrap().len());
+    /// assert_eq!(utf_c::decompress_with(&compressed_bytes, &options).unwrap(), text.as_bytes());
+    /// ```
+    pub const fn prefix_cache(mut self, prefix_cache: bool) -> Self {
+        self.prefix_cache = prefix_cache;
+        self
+    }
+
+    /// Returns whether the last prefixes are remembered.
+    pub const fn get_prefix_cache(&self) -> bool {
+        self.prefix_cache
+    }
+
+    /// Sets whether CJK, Kana and Hangul characters are encoded with 2 bytes in a window of 32768 code points.
+    ///
+    /// Unlike the 2-byte prefixes of these characters, which only cover 64 code points each,
+    /// a window covers a whole script and the fullwidth punctuation, so e.g. Chinese text needs about 2/3 of its UTF-8 length.
+    ///
+    /// # Example
+    /// ```
+    /// use utf_c::Options;
+    ///
+    /// let text = "日本語のテキストと中文文本";
+    /// let options = Options::new().wide_windows(true);
+    /// let compressed_bytes = utf_c::compress_with(text, &options).unwrap();
+    /// assert_eq!(compressed_bytes.len(), 1 + 2 + 13 * 2);
+    /// //                                 |   |   |-Characters
+    /// //                                 |   |-Window
+    /// //                                 |-Length
+    /// assert_eq!(utf_c::decompress(&compressed_bytes).unwrap(), text.as_bytes());
+    /// ```
+    pub const fn wide_windows(mut self, wide_windows: bool) -> Self {
+        self.wide_windows = wide_windows;
+        self
+    }
+
+    /// Returns whether CJK, Kana and Hangul characters are encoded in a window.
+    pub const fn get_wide_windows(&self) -> bool {
+        self.wide_windows
+    }
+
+    /// Sets whether the characters of small alphabets are encoded with 1 byte in a window of 128 code points.
+    ///
+    /// The window is aligned to the Unicode block, so that e.g. the letters and vowel signs of Devanagari,
+    /// which have different prefixes, are in the same window. This is used for Greek, Cyrillic, Armenian,
+    /// Hebrew, Arabic, the Indic scripts, Thai and Lao.
+    ///
+    /// # Example
+    /// ```
+    /// use utf_c::Options;
+    ///
+    /// let text = "नमस्ते दुनिया";
+    /// let options = Options::new().narrow_windows(true);
+    /// let compressed_bytes = utf_c::compress_with(text, &options).unwrap();
+    /// assert_eq!(compressed_bytes.len(), 1 + 2 + 13);
+    /// //                                 |   |   |-Characters
+    /// //                                 |   |-Window
+    /// //                                 |-Length
+    /// assert_eq!(utf_c::decompress(&compressed_bytes).unwrap(), text.as_bytes());
+    /// ```
+    pub const fn narrow_windows(mut self, narrow_windows: bool) -> Self {
+        self.narrow_windows = narrow_windows;
+        self
+    }
+
+    /// Returns whether the characters of small alphabets are encoded in a window.
+    pub const fn get_narrow_windows(&self) -> bool {
+        self.narrow_windows
+    }
+
+    /// Sets whether emoji sequences are encoded with shorter codes, that don't change the prefix.
+    ///
+    /// This is used for U+200D (ZERO WIDTH JOINER) and U+FE0F (VARIATION SELECTOR-16) with 1 byte,
+    /// the skin tone modifiers with 2 bytes and the pairs of regional indicators (flags) with 3 bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use utf_c::Options;
+    ///
+    /// let text = "👨‍👩‍👧";
+    /// let options = Options::new().emoji(true);
+    /// let compressed_bytes = utf_c::compress_with(text, &options).unwrap();
+    /// assert_eq!(compressed_bytes.len(), 1 + 4 + 2 + 2);
+    /// //                                 |   |   |   |-ZWJ and 👧
+    /// //                                 |   |   |-ZWJ and 👩
+    /// //                                 |   |-👨
+    /// //                                 |-Length
+    /// assert_eq!(utf_c::decompress(&compressed_bytes).unwrap(), text.as_bytes());
+    /// ```
+    pub const fn emoji(mut self, emoji: bool) -> Self {
+        self.emoji = emoji;
+        self
+    }
+
+    /// Returns whether emoji sequences are encoded with shorter codes.
+    pub const fn get_emoji(&self) -> bool {
+        self.emoji
+    }
+}
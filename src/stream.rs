@@ -0,0 +1,382 @@
+//! Incremental compression and decompression without [`std::io`](https://doc.rust-lang.org/std/io/index.html).
+//!
+//! Because the total length is unknown in advance, a stream uses its own framing instead of the length header of [`compress`](crate::compress):
+//! ```text
+//! stream := chunk* end
+//! chunk  := length (u16, little-endian, 1..=65535) + compressed characters
+//! end    := 0x00 0x00
+//! ```
+//! Each chunk ends at a character boundary and continues with the last prefix of the previous chunk.
+
+use alloc::vec::Vec;
+
+use crate::{context::Context, utf8, CompressError, DecompressError, Options};
+
+/// The length of the compressed characters at which a chunk is written.
+pub(crate) const CHUNK_LEN: usize = 16 * 1024;
+/// The length of a chunk header.
+const CHUNK_HEADER_LEN: usize = 2;
+/// The end of a stream (a chunk with the length `0`).
+const END: [u8; CHUNK_HEADER_LEN] = [0, 0];
+
+/// The number of bytes that were read from the input and written to the output by a single call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    pub read: usize,
+    pub written: usize,
+}
+
+/// A push-based compressor that produces a stream.
+///
+/// The text can be passed in arbitrary chunks, even if a character is split between them.
+///
+/// # Example
+/// ```
+/// use utf_c::stream::Compressor;
+///
+/// let (mut compressor, mut compressed_bytes) = (Compressor::new(), Vec::new());
+/// compressor.update("Hello ".as_bytes(), &mut compressed_bytes).unwrap();
+/// compressor.update(&"Wörld".as_bytes()[..2], &mut compressed_bytes).unwrap();
+/// compressor.update(&"Wörld".as_bytes()[2..], &mut compressed_bytes).unwrap();
+/// compressor.finish(&mut compressed_bytes).unwrap();
+/// assert_eq!(compressed_bytes, [12, 0, 72, 101, 108, 108, 111, 32, 87, 195, 182, 114, 108, 100, 0, 0]);
+/// //                            |      |-Characters                                         |-End
+/// //                            |-Length
+/// ```
+#[derive(Default)]
+pub struct Compressor {
+    chunk: Vec<u8>,
+    /// The bytes of an incomplete character at the end of the last input.
+    pending: [u8; utf8::MAX_UTF8_BYTES],
+    pending_len: usize,
+    context: Context,
+    /// The number of compressed bytes of the input (for the offset of an error).
+    offset: usize,
+}
+
+impl Compressor {
+    /// Creates a new compressor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compresses the input and appends each full chunk to `output`.
+    ///
+    /// The whole input is always read. An incomplete character at the end is kept for the next call.
+    pub fn update(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> Result<Progress, CompressError> {
+        let progress = Progress { read: input.len(), written: output.len() };
+
+        // First, try to complete the character of the last input.
+        if self.pending_len > 0 && !input.is_empty() {
+            let utf8_len = utf8::len_from_first_byte(self.pending[0]).max(self.pending_len + 1);
+            let input_len = (utf8_len - self.pending_len).min(input.len());
+            self.pending[self.pending_len..(self.pending_len + input_len)].copy_from_slice(&input[..input_len]);
+            self.pending_len += input_len;
+            input = &input[input_len..];
+
+            if self.pending_len == utf8_len {
+                let pending = self.pending;
+                self.pending_len = 0;
+                self.compress(&pending[..utf8_len])?;
+            }
+        }
+
+        loop {
+            // Make sure that the longest character fits into the current chunk.
+            if self.chunk.len() + utf8::MAX_UTF8_BYTES > CHUNK_LEN {
+                self.write_chunk(output);
+            }
+
+            if input.is_empty() {
+                break;
+            }
+
+            // Compressed characters are never longer than the original characters,
+            // so the current chunk can't be longer than `CHUNK_LEN`.
+            let max_len = CHUNK_LEN - self.chunk.len();
+            let value = &input[..input.len().min(max_len)];
+            let value_len = utf8::complete_len(value);
+            if value_len == 0 {
+                // Only an incomplete character is left, so keep it for the next input.
+                self.pending[..input.len()].copy_from_slice(input);
+                self.pending_len = input.len();
+                break;
+            }
+
+            self.compress(&value[..value_len])?;
+            input = &input[value_len..];
+        }
+
+        Ok(Progress { written: output.len() - progress.written, ..progress })
+    }
+
+    /// Appends the current chunk to `output`, even if it is not full, and returns the number of written bytes.
+    pub fn flush(&mut self, output: &mut Vec<u8>) -> usize {
+        let output_len = output.len();
+        self.write_chunk(output);
+        output.len() - output_len
+    }
+
+    /// Appends the current chunk and the end of the stream to `output`, and returns the number of written bytes.
+    ///
+    /// Returns an error if the last character is incomplete.
+    /// Afterwards, the compressor can be used for a new stream.
+    pub fn finish(&mut self, output: &mut Vec<u8>) -> Result<usize, CompressError> {
+        if self.pending_len > 0 {
+            let err_result = self.pending[..self.pending_len].to_vec();
+            return Err(CompressError::InvalidOrMissingPrefix { at: self.offset, bytes: err_result });
+        }
+
+        let written = self.flush(output) + END.len();
+        output.extend_from_slice(&END);
+        self.context = Context::default();
+        self.offset = 0;
+        Ok(written)
+    }
+
+    /// Appends the current chunk (if it is not empty) to `output`.
+    fn write_chunk(&mut self, output: &mut Vec<u8>) {
+        if self.chunk.is_empty() {
+            return;
+        }
+
+        let chunk_len = self.chunk.len() as u16;
+        output.extend_from_slice(&chunk_len.to_le_bytes());
+        output.extend_from_slice(&self.chunk);
+        self.chunk.clear();
+    }
+
+    /// Compresses complete characters and appends them to the current chunk.
+    #[inline]
+    fn compress(&mut self, value: &[u8]) -> Result<(), CompressError> {
+        crate::compress_body::<_, true>(value, &Options::new(), &mut self.context, &mut self.chunk).map_err(|err| err.with_offset(self.offset))?;
+        self.offset += value.len();
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    /// The header of the next chunk (with the number of bytes already read).
+    Header([u8; CHUNK_HEADER_LEN], usize),
+    /// The compressed characters of a chunk (with the length of the chunk).
+    Chunk(usize),
+    /// The end of the stream has been read.
+    End,
+}
+
+/// A push-based decompressor that reads a stream.
+///
+/// Only one chunk is kept in memory at a time, and each character is validated while decoding.
+///
+/// # Example
+/// ```
+/// use utf_c::stream::Decompressor;
+///
+/// const BYTES: [u8; 16] = [12, 0, 72, 101, 108, 108, 111, 32, 87, 195, 182, 114, 108, 100, 0, 0];
+/// let (mut decompressor, mut decompressed_bytes) = (Decompressor::new(), Vec::new());
+/// for bytes in BYTES.chunks(3) {
+///     decompressor.update(bytes, &mut decompressed_bytes).unwrap();
+/// }
+/// decompressor.finish().unwrap();
+/// assert_eq!(decompressed_bytes, "Hello Wörld".as_bytes());
+/// ```
+pub struct Decompressor {
+    state: State,
+    chunk: Vec<u8>,
+    context: Context,
+    /// The number of bytes of the stream before the current header or chunk (for the offset of an error).
+    offset: usize,
+}
+
+impl Default for Decompressor {
+    fn default() -> Self {
+        Self {
+            state: State::Header([0; CHUNK_HEADER_LEN], 0),
+            chunk: Vec::new(),
+            context: Context::default(),
+            offset: 0,
+        }
+    }
+}
+
+impl Decompressor {
+    /// Creates a new decompressor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the end of the stream has been read, otherwise `false`.
+    #[inline]
+    pub fn is_end(&self) -> bool {
+        matches!(self.state, State::End)
+    }
+
+    /// Returns the number of bytes that are needed to finish the current header or chunk.
+    /// 
+    /// This can be used to read the input without reading past the end of the stream.
+    #[inline]
+    pub fn next_len(&self) -> usize {
+        match self.state {
+            State::Header(_, header_len) => CHUNK_HEADER_LEN - header_len,
+            State::Chunk(chunk_len) => chunk_len - self.chunk.len(),
+            State::End => 0,
+        }
+    }
+
+    /// Decompresses the input and appends each complete chunk to `output`.
+    ///
+    /// The input is read up to the end of the stream, so that other data can follow the stream.
+    pub fn update(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> Result<Progress, DecompressError> {
+        let progress = Progress { read: input.len(), written: output.len() };
+
+        while !input.is_empty() {
+            match self.state {
+                State::Header(mut header, header_len) => {
+                    let input_len = (CHUNK_HEADER_LEN - header_len).min(input.len());
+                    header[header_len..(header_len + input_len)].copy_from_slice(&input[..input_len]);
+                    input = &input[input_len..];
+                    self.offset += input_len;
+
+                    self.state = match (header_len + input_len, u16::from_le_bytes(header)) {
+                        (CHUNK_HEADER_LEN, 0) => State::End,
+                        (CHUNK_HEADER_LEN, chunk_len) => State::Chunk(chunk_len as usize),
+                        (header_len, _) => State::Header(header, header_len),
+                    };
+                },
+                State::Chunk(chunk_len) => {
+                    if self.chunk.is_empty() && input.len() >= chunk_len {
+                        // The whole chunk is available, so we don't have to copy it.
+                        self.decompress(&input[..chunk_len], output)?;
+                        input = &input[chunk_len..];
+                        continue;
+                    }
+
+                    let input_len = (chunk_len - self.chunk.len()).min(input.len());
+                    self.chunk.extend_from_slice(&input[..input_len]);
+                    input = &input[input_len..];
+
+                    if self.chunk.len() == chunk_len {
+                        let chunk = core::mem::take(&mut self.chunk);
+                        let result = self.decompress(&chunk, output);
+                        self.chunk = chunk;
+                        self.chunk.clear();
+                        result?;
+                    }
+                },
+                State::End => break,
+            }
+        }
+
+        Ok(Progress { read: progress.read - input.len(), written: output.len() - progress.written })
+    }
+
+    /// Returns an error if the end of the stream has not been read.
+    ///
+    /// Afterwards, the decompressor can be used for a new stream.
+    pub fn finish(&mut self) -> Result<(), DecompressError> {
+        if !self.is_end() {
+            return Err(DecompressError::MissingBytes);
+        }
+
+        *self = Self { chunk: core::mem::take(&mut self.chunk), ..Self::default() };
+        Ok(())
+    }
+
+    /// Decompresses a complete chunk and prepares the header of the next one.
+    #[inline]
+    fn decompress(&mut self, chunk: &[u8], output: &mut Vec<u8>) -> Result<(), DecompressError> {
+        self.state = State::Header([0; CHUNK_HEADER_LEN], 0);
+        crate::decompress_body::<_, true>(chunk, &mut self.context, output, usize::MAX).map_err(|err| err.with_offset(self.offset))?;
+        self.offset += chunk.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn compressor() {
+        let text = "Hello עוֹלָם 👋";
+        let compressed_bytes = crate::compress(text).unwrap();
+        let compressed_chars = &compressed_bytes[1..];
+
+        // Each character is split between multiple inputs.
+        for input_len in 1..=text.len() {
+            let (mut compressor, mut stream) = (super::Compressor::new(), Vec::new());
+            for bytes in text.as_bytes().chunks(input_len) {
+                let progress = compressor.update(bytes, &mut stream).unwrap();
+                assert_eq!(progress, super::Progress { read: bytes.len(), written: 0 });
+            }
+            compressor.finish(&mut stream).unwrap();
+
+            let expected_stream = [&(compressed_chars.len() as u16).to_le_bytes(), compressed_chars, &super::END].concat();
+            assert_eq!(stream, expected_stream, "failed with input length {}", input_len);
+        }
+
+        // Multiple chunks
+        let text = "α".repeat(super::CHUNK_LEN);
+        let (mut compressor, mut stream) = (super::Compressor::new(), Vec::new());
+        compressor.update(text.as_bytes(), &mut stream).unwrap();
+        compressor.flush(&mut stream);
+        compressor.update(text.as_bytes(), &mut stream).unwrap();
+        compressor.finish(&mut stream).unwrap();
+
+        let (mut chunks, mut compressed_chars) = (&stream[..], Vec::new());
+        while chunks != super::END {
+            let chunk_len = u16::from_le_bytes([chunks[0], chunks[1]]) as usize;
+            assert!(chunk_len <= super::CHUNK_LEN);
+            compressed_chars.extend_from_slice(&chunks[2..(2 + chunk_len)]);
+            chunks = &chunks[(2 + chunk_len)..];
+        }
+        let compressed_bytes = crate::compress(text.repeat(2)).unwrap();
+        assert_eq!(compressed_chars, compressed_bytes[(compressed_bytes.len() - compressed_chars.len())..]);
+        assert_eq!(compressed_chars.len(), 1 + (2 * super::CHUNK_LEN));
+
+        // Incomplete character
+        let (mut compressor, mut stream) = (super::Compressor::new(), Vec::new());
+        compressor.update(&"ö".as_bytes()[..1], &mut stream).unwrap();
+        assert!(compressor.finish(&mut stream).is_err());
+
+        // Invalid character
+        let (mut compressor, mut stream) = (super::Compressor::new(), Vec::new());
+        compressor.update(&[72, 195], &mut stream).unwrap();
+        let err = compressor.update(&[182, 72, 0b10000000], &mut stream).unwrap_err();
+        assert_eq!(err, crate::CompressError::InvalidOrMissingPrefix { at: 4, bytes: vec![0b10000000] });
+    }
+
+    #[test]
+    fn decompressor() {
+        let text = "Hello עוֹלָם 👋\n".repeat(4 * 1024);
+        let (mut compressor, mut stream) = (super::Compressor::new(), Vec::new());
+        compressor.update(text.as_bytes(), &mut stream).unwrap();
+        compressor.finish(&mut stream).unwrap();
+        let stream_len = stream.len();
+        stream.extend_from_slice(b"Next");
+
+        for input_len in [1, 2, 3, 1000, stream.len()] {
+            let (mut decompressor, mut decompressed_bytes, mut read) = (super::Decompressor::new(), Vec::new(), 0);
+            for bytes in stream.chunks(input_len) {
+                read += decompressor.update(bytes, &mut decompressed_bytes).unwrap().read;
+            }
+            assert_eq!(read, stream_len, "failed with input length {}", input_len);
+            assert_eq!(decompressor.finish(), Ok(()), "failed with input length {}", input_len);
+            assert!(decompressed_bytes == text.as_bytes(), "failed with input length {}", input_len);
+        }
+
+        // Incomplete stream
+        let mut decompressor = super::Decompressor::new();
+        decompressor.update(&stream[..(stream_len - 1)], &mut Vec::new()).unwrap();
+        assert_eq!(decompressor.finish(), Err(crate::DecompressError::MissingBytes));
+
+        // Invalid character
+        let mut decompressor = super::Decompressor::new();
+        let err = decompressor.update(&[2, 0, 195, 182, 3, 0, 72, 215, 72, 0, 0], &mut Vec::new()).unwrap_err();
+        assert_eq!(err, crate::DecompressError::InvalidUtf8 { at: 7, bytes: vec![195, 215] });
+
+        // Prefix at the end of a chunk
+        let mut decompressor = super::Decompressor::new();
+        let err = decompressor.update(&[2, 0, 195, 182, 2, 0, 72, 215, 0, 0], &mut Vec::new()).unwrap_err();
+        assert_eq!(err, crate::DecompressError::Truncated { at: 7 });
+    }
+}
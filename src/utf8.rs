@@ -6,6 +6,32 @@ const UTF8_4_BYTES: [u8; 4] = [ 0b11110, 0b10, 0b10, 0b10 ]; // 10000-1FFFF
 /// The maximum length of bytes per character.
 pub const MAX_UTF8_BYTES: usize = 4;
 
+/// An owned copy of a prefix, so that it can be kept between multiple inputs.
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Prefix {
+    bytes: [u8; MAX_UTF8_BYTES - 1],
+    len: u8,
+}
+
+impl Prefix {
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        // We can use `unsafe { v.get_unchecked(...) }`, because the length is never greater than the array.
+        unsafe { self.bytes.get_unchecked(..(self.len as usize)) }
+    }
+}
+
+impl From<&[u8]> for Prefix {
+    #[inline]
+    fn from(value: &[u8]) -> Self {
+        let mut prefix = Self::default();
+        let len = value.len().min(MAX_UTF8_BYTES - 1);
+        prefix.bytes[..len].copy_from_slice(&value[..len]);
+        prefix.len = len as u8;
+        prefix
+    }
+}
+
 #[derive(PartialEq, Clone, Copy)]
 #[repr(i8)]
 pub enum Unicode {
@@ -39,6 +65,18 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Returns the code point of the character (only the bit patterns are checked).
+    #[inline]
+    pub fn code_point(&self) -> u32 {
+        let first_bits = match self.1 {
+            Unicode::Unknown | Unicode::Range00000_0007F => unreachable!(),
+            Unicode::Range00080_007FF => self.0[0] & 0b00011111,
+            Unicode::Range00800_0FFFF => self.0[0] & 0b00001111,
+            Unicode::Range10000_1FFFF => self.0[0] & 0b00000111,
+        };
+        self.0[1..self.len()].iter().fold(first_bits as u32, |code_point, &byte| (code_point << 6) | (byte & 0b00111111) as u32)
+    }
+
     #[inline]
     pub fn prefix(&self) -> &'a [u8] {
         match self.1 {
@@ -76,6 +114,93 @@ impl<'a> From<&'a [u8]> for Value<'a> {
     }
 }
 
+/// Returns `true` if the byte is a continuation byte (`0b10xxxxxx`), otherwise `false`.
+#[inline(always)]
+pub const fn is_continuation(byte: u8) -> bool {
+    (byte >> 6) == UTF8_2_BYTES[1]
+}
+
+/// Returns the length of a character based on its first byte, or `0` if the byte can't be the first byte of a character.
+#[inline(always)]
+pub const fn len_from_first_byte(byte: u8) -> usize {
+    match byte.leading_ones() {
+        0 => 1,
+        2 => 2,
+        3 => 3,
+        4 => 4,
+        _ => 0,
+    }
+}
+
+/// Returns the length of the bytes without an incomplete character at the end.
+/// 
+/// Invalid bytes are not removed, because they can't become a valid character with more bytes.
+#[inline]
+pub fn complete_len(bytes: &[u8]) -> usize {
+    let len = bytes.len();
+    // Search for the first byte of the last character (at most `MAX_UTF8_BYTES - 1` continuation bytes).
+    for idx in (len.saturating_sub(MAX_UTF8_BYTES)..len).rev() {
+        if !is_continuation(bytes[idx]) {
+            let utf8_len = len_from_first_byte(bytes[idx]);
+            if utf8_len > (len - idx) {
+                return idx;
+            }
+            break;
+        }
+    }
+    len
+}
+
+/// The reason why a prefix is not well-formed according to RFC 3629.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PrefixError {
+    /// The character could be encoded with fewer bytes (e.g. `C0 80`).
+    Overlong,
+    /// The character is a UTF-16 surrogate (U+D800-U+DFFF).
+    Surrogate,
+    /// The character is above U+10FFFF (e.g. the lead bytes `F5`-`F7`).
+    OutOfRange,
+}
+
+/// Checks if the prefix (all bytes of a character except the last one) is well-formed according to RFC 3629.
+/// 
+/// Unlike the bit patterns of `Value`, this also rejects overlong encodings, UTF-16 surrogates and code points above U+10FFFF.
+/// The prefix must match the bit patterns of `Value`.
+#[inline]
+pub const fn check_prefix(prefix: &[u8]) -> Result<(), PrefixError> {
+    match prefix {
+        // 2 bytes
+        [0xC0..=0xC1] => Err(PrefixError::Overlong),
+        // 3 bytes
+        [0xE0, 0x80..=0x9F] => Err(PrefixError::Overlong),
+        [0xED, 0xA0..=0xBF] => Err(PrefixError::Surrogate),
+        // 4 bytes
+        [0xF0, 0x80..=0x8F, _] => Err(PrefixError::Overlong),
+        [0xF4, 0x90..=0xBF, _] |
+        [0xF5..=0xF7, _, _] => Err(PrefixError::OutOfRange),
+        _ => Ok(()),
+    }
+}
+
+/// Returns `true` if the prefix (all bytes of a character except the last one) is well-formed according to RFC 3629, otherwise `false`.
+/// 
+/// Unlike `check_prefix`, this also checks the bit patterns.
+#[inline]
+pub const fn is_valid_prefix(prefix: &[u8]) -> bool {
+    matches!(prefix, 
+        // 2 bytes
+        [0xC2..=0xDF] |
+        // 3 bytes
+        [0xE0, 0xA0..=0xBF] |
+        [0xE1..=0xEC | 0xEE..=0xEF, 0x80..=0xBF] |
+        [0xED, 0x80..=0x9F] |
+        // 4 bytes
+        [0xF0, 0x90..=0xBF, 0x80..=0xBF] |
+        [0xF1..=0xF3, 0x80..=0xBF, 0x80..=0xBF] |
+        [0xF4, 0x80..=0x8F, 0x80..=0xBF]
+    )
+}
+
 #[inline(always)]
 const fn is_1_bytes(values: &[u8]) -> bool {
     (values[0] >> 7) == UTF8_1_BYTES[0]
@@ -0,0 +1,150 @@
+use crate::{
+    buffer::Buffer,
+    context::{self, Context, Window},
+    helper, utf8, CompressError, DecompressError, Options,
+};
+
+/// Returns the window for a character that is not in the current window, if one of the enabled windows should be used.
+///
+/// Windows are only used for scripts with many characters, where the prefixes change often.
+#[inline]
+pub(crate) fn select(options: &Options, code_point: u32) -> Option<Window> {
+    match code_point {
+        // Kana and CJK (U+2E00-U+ACFF)
+        0x3040..=0x9FFF if options.get_wide_windows() => Some(Window::Wide(0x2E)),
+        // The halfwidth and fullwidth forms (in each wide window), mostly used with CJK
+        0xFF00..=0xFFEF if options.get_wide_windows() => Some(Window::Wide(0x2E)),
+        // Hangul (U+8000-U+FFFF)
+        0xAC00..=0xD7A3 if options.get_wide_windows() => Some(Window::Wide(0x80)),
+        // Greek, Cyrillic, Armenian, Hebrew, Arabic, Syriac, Thaana and NKo (2 bytes per character in UTF-8),
+        // and the Indic scripts, Thai and Lao (3 bytes per character in UTF-8), aligned to the Unicode block
+        0x0370..=0x07FF | 0x0900..=0x0EFF if options.get_narrow_windows() => Some(Window::Narrow((code_point >> 7) as u8)),
+        _ => None,
+    }
+}
+
+/// Writes the code to enter the window.
+#[inline]
+pub(crate) fn enter<B: Buffer>(window: Window, context: &mut Context, result: &mut B) {
+    if context.last_window == Some(window) {
+        result.push(context::LAST_WINDOW);
+    } else {
+        match window {
+            Window::Wide(selector) => result.extend_from_slice(&[context::WIDE_WINDOW, selector]),
+            Window::Narrow(selector) => result.extend_from_slice(&[context::NARROW_WINDOW, selector]),
+        }
+    }
+    context.enter(window);
+}
+
+/// Compresses the characters in the current window and returns the number of read bytes or `CompressError`.
+///
+/// The window is left if a character is neither in the current window nor in another window.
+pub(crate) fn compress<B: Buffer, const STRICT: bool>(value: &[u8], options: &Options, context: &mut Context, result: &mut B) -> Result<usize, CompressError> {
+    let Some(mut window) = context.window else {
+        return Ok(0);
+    };
+
+    let mut idx = 0;
+    while idx < value.len() {
+        let rest = &value[idx..];
+        if !helper::test_sign_bit(rest[0]) {
+            // ASCII characters are written as usual, but `ESC` is written twice.
+            let ascii_len = helper::find_pos_byte_idx(rest).unwrap_or(rest.len());
+            for part in rest[..ascii_len].split_inclusive(|&byte| byte == context::WINDOW_EXIT) {
+                result.extend_from_slice(part);
+                if part.last() == Some(&context::WINDOW_EXIT) {
+                    result.push(context::WINDOW_EXIT);
+                }
+            }
+            idx += ascii_len;
+            continue;
+        }
+
+        let utf8_value = utf8::Value::from(rest);
+        if matches!(utf8_value.unicode(), utf8::Unicode::Unknown | utf8::Unicode::Range10000_1FFFF) {
+            // Leave the window, so that the character (or the error) is handled as usual.
+            break;
+        }
+        if STRICT && let Err(err) = utf8::check_prefix(utf8_value.prefix()) {
+            return Err(CompressError::from_prefix_error(err, idx));
+        }
+
+        let code_point = utf8_value.code_point();
+        let offset = match window.offset(code_point) {
+            Some(offset) => offset,
+            None => match select(options, code_point).and_then(|next_window| Some((next_window, next_window.offset(code_point)?))) {
+                Some((next_window, offset)) => {
+                    result.push(context::WINDOW_EXIT);
+                    enter(next_window, context, result);
+                    window = next_window;
+                    offset
+                },
+                None => break,
+            },
+        };
+
+        match window {
+            Window::Wide(_) => result.extend_from_slice(&[0b10000000 | (offset >> 8) as u8, offset as u8]),
+            Window::Narrow(_) => result.push(0b10000000 | offset as u8),
+        }
+        idx += utf8_value.len();
+    }
+
+    if idx < value.len() {
+        result.push(context::WINDOW_EXIT);
+        context.window = None;
+    }
+    Ok(idx)
+}
+
+/// Decompresses the characters in the current window and returns the number of read bytes or `DecompressError`.
+///
+/// Each character is always validated, because it is not copied from the compressed bytes.
+/// Stops as soon as `result` contains `limit` bytes.
+pub(crate) fn decompress<B: Buffer>(value: &[u8], context: &mut Context, result: &mut B, limit: usize) -> Result<usize, DecompressError> {
+    let Some(window) = context.window else {
+        return Ok(0);
+    };
+
+    let mut idx = 0;
+    while idx < value.len() && result.len() < limit {
+        let byte = value[idx];
+        if byte == context::WINDOW_EXIT {
+            if value.get(idx + 1) == Some(&context::WINDOW_EXIT) {
+                result.push(context::WINDOW_EXIT);
+                idx += 2;
+                continue;
+            }
+
+            context.window = None;
+            return Ok(idx + 1);
+        }
+
+        if !helper::test_sign_bit(byte) {
+            let ascii_len = value[idx..].iter().position(|&byte| helper::test_sign_bit(byte) || byte == context::WINDOW_EXIT).unwrap_or(value.len() - idx);
+            let ascii_len = ascii_len.min(limit - result.len());
+            result.extend_from_slice(&value[idx..(idx + ascii_len)]);
+            idx += ascii_len;
+            continue;
+        }
+
+        let (offset, len) = match window {
+            Window::Wide(_) => {
+                let Some(&low_byte) = value.get(idx + 1) else {
+                    return Err(DecompressError::Truncated { at: idx });
+                };
+                ((((byte & 0b01111111) as u32) << 8) | low_byte as u32, 2)
+            },
+            Window::Narrow(_) => ((byte & 0b01111111) as u32, 1),
+        };
+
+        let Some(utf8_char) = char::from_u32(window.code_point(offset)) else {
+            return Err(DecompressError::InvalidUtf8 { at: idx, bytes: value[idx..(idx + len)].to_vec() });
+        };
+        result.extend_from_slice(utf8_char.encode_utf8(&mut [0; utf8::MAX_UTF8_BYTES]).as_bytes());
+        idx += len;
+    }
+
+    Ok(idx)
+}